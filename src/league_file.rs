@@ -0,0 +1,186 @@
+/// The kinds of files found in WADs, identified by their magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LeagueFileKind {
+    Animation,
+    Jpeg,
+    LuaObj,
+    MapGeometry,
+    Png,
+    Preload,
+    PropertyBin,
+    PropertyBinOverride,
+    RiotStringTable,
+    SimpleSkin,
+    Skeleton,
+    StaticMeshAscii,
+    StaticMeshBinary,
+    Texture,
+    TextureDds,
+    WorldGeometry,
+    WwiseBank,
+    WwisePackage,
+    Unknown,
+}
+
+/// Magic bytes at the start of a file, ordered so that longer magics sharing a prefix come first
+const MAGICS: &[(&[u8], LeagueFileKind)] = &[
+    (b"r3d2Mesh", LeagueFileKind::StaticMeshBinary),
+    (b"r3d2sklt", LeagueFileKind::Skeleton),
+    (b"r3d2anmd", LeagueFileKind::Animation),
+    (b"r3d2canm", LeagueFileKind::Animation),
+    (b"r3d2", LeagueFileKind::WwisePackage),
+    (b"PROP", LeagueFileKind::PropertyBin),
+    (b"PTCH", LeagueFileKind::PropertyBinOverride),
+    (b"OEGM", LeagueFileKind::MapGeometry),
+    (b"WGEO", LeagueFileKind::WorldGeometry),
+    (b"RST", LeagueFileKind::RiotStringTable),
+    (b"TEX\0", LeagueFileKind::Texture),
+    (b"DDS ", LeagueFileKind::TextureDds),
+    (b"BKHD", LeagueFileKind::WwiseBank),
+    (b"\x89PNG\r\n\x1a\n", LeagueFileKind::Png),
+    (b"\xFF\xD8\xFF", LeagueFileKind::Jpeg),
+    (b"\x1BLua", LeagueFileKind::LuaObj),
+    (b"PreLoadBuildingBlocks = {", LeagueFileKind::Preload),
+    (b"[ObjectBegin]", LeagueFileKind::StaticMeshAscii),
+    (&[0x33, 0x22, 0x11, 0x00], LeagueFileKind::SimpleSkin),
+];
+
+/// Magic of the current skeleton format, stored after the size of the file
+const SKELETON_MAGIC: [u8; 4] = 0x22FD4FC3u32.to_le_bytes();
+
+impl LeagueFileKind {
+    /// The extension files of this kind are usually stored with
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            LeagueFileKind::Animation => Some("anm"),
+            LeagueFileKind::Jpeg => Some("jpg"),
+            LeagueFileKind::LuaObj => Some("luaobj"),
+            LeagueFileKind::MapGeometry => Some("mapgeo"),
+            LeagueFileKind::Png => Some("png"),
+            LeagueFileKind::Preload => Some("preload"),
+            LeagueFileKind::PropertyBin | LeagueFileKind::PropertyBinOverride => Some("bin"),
+            LeagueFileKind::RiotStringTable => Some("stringtable"),
+            LeagueFileKind::SimpleSkin => Some("skn"),
+            LeagueFileKind::Skeleton => Some("skl"),
+            LeagueFileKind::StaticMeshAscii => Some("sco"),
+            LeagueFileKind::StaticMeshBinary => Some("scb"),
+            LeagueFileKind::Texture => Some("tex"),
+            LeagueFileKind::TextureDds => Some("dds"),
+            LeagueFileKind::WorldGeometry => Some("wgeo"),
+            LeagueFileKind::WwiseBank => Some("bnk"),
+            LeagueFileKind::WwisePackage => Some("wpk"),
+            LeagueFileKind::Unknown => None,
+        }
+    }
+
+    /// Returns the kind of files stored with `extension`, the inverse of [`LeagueFileKind::extension`]
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.trim_start_matches('.').to_lowercase().as_str() {
+            "anm" => LeagueFileKind::Animation,
+            "jpg" | "jpeg" => LeagueFileKind::Jpeg,
+            "luaobj" => LeagueFileKind::LuaObj,
+            "mapgeo" => LeagueFileKind::MapGeometry,
+            "png" => LeagueFileKind::Png,
+            "preload" => LeagueFileKind::Preload,
+            "bin" => LeagueFileKind::PropertyBin,
+            "stringtable" => LeagueFileKind::RiotStringTable,
+            "skn" => LeagueFileKind::SimpleSkin,
+            "skl" => LeagueFileKind::Skeleton,
+            "sco" => LeagueFileKind::StaticMeshAscii,
+            "scb" => LeagueFileKind::StaticMeshBinary,
+            "tex" => LeagueFileKind::Texture,
+            "dds" => LeagueFileKind::TextureDds,
+            "wgeo" => LeagueFileKind::WorldGeometry,
+            "bnk" => LeagueFileKind::WwiseBank,
+            "wpk" => LeagueFileKind::WwisePackage,
+            _ => LeagueFileKind::Unknown,
+        }
+    }
+}
+
+/// Identifies the kind of a file from its magic bytes
+pub fn identify(data: &[u8]) -> LeagueFileKind {
+    if let Some((_, kind)) = MAGICS.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return *kind;
+    }
+
+    match data.get(4..8) {
+        Some(magic) if magic == SKELETON_MAGIC => LeagueFileKind::Skeleton,
+        _ => LeagueFileKind::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{identify, LeagueFileKind};
+
+    #[test]
+    fn test_identify() {
+        let files: &[(&[u8], LeagueFileKind, Option<&str>)] = &[
+            (b"PROP\x01\0\0\0", LeagueFileKind::PropertyBin, Some("bin")),
+            (
+                b"PTCH\x01\0\0\0",
+                LeagueFileKind::PropertyBinOverride,
+                Some("bin"),
+            ),
+            (
+                b"r3d2Mesh\x02\0",
+                LeagueFileKind::StaticMeshBinary,
+                Some("scb"),
+            ),
+            (b"r3d2sklt\x02\0", LeagueFileKind::Skeleton, Some("skl")),
+            (
+                b"\0\x10\0\0\xC3\x4F\xFD\x22",
+                LeagueFileKind::Skeleton,
+                Some("skl"),
+            ),
+            (b"r3d2canm\x01\0", LeagueFileKind::Animation, Some("anm")),
+            (b"r3d2\x01\0\0\0", LeagueFileKind::WwisePackage, Some("wpk")),
+            (
+                b"\x33\x22\x11\x00\x01\0",
+                LeagueFileKind::SimpleSkin,
+                Some("skn"),
+            ),
+            (b"OEGM\x0D\0", LeagueFileKind::MapGeometry, Some("mapgeo")),
+            (
+                b"RST\x05",
+                LeagueFileKind::RiotStringTable,
+                Some("stringtable"),
+            ),
+            (b"TEX\0\x04\0", LeagueFileKind::Texture, Some("tex")),
+            (b"DDS \x7C\0", LeagueFileKind::TextureDds, Some("dds")),
+            (b"BKHD\x18\0", LeagueFileKind::WwiseBank, Some("bnk")),
+            (b"\x89PNG\r\n\x1a\n\0", LeagueFileKind::Png, Some("png")),
+            (b"\xFF\xD8\xFF\xE0", LeagueFileKind::Jpeg, Some("jpg")),
+            (b"\x1BLuaQ", LeagueFileKind::LuaObj, Some("luaobj")),
+            (
+                b"PreLoadBuildingBlocks = {\n",
+                LeagueFileKind::Preload,
+                Some("preload"),
+            ),
+            (b"", LeagueFileKind::Unknown, None),
+            (b"unknown data", LeagueFileKind::Unknown, None),
+        ];
+
+        for (data, kind, extension) in files {
+            assert_eq!(identify(data), *kind);
+            assert_eq!(kind.extension(), *extension);
+        }
+    }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(
+            LeagueFileKind::from_extension(".SKN"),
+            LeagueFileKind::SimpleSkin
+        );
+        assert_eq!(
+            LeagueFileKind::from_extension("bin"),
+            LeagueFileKind::PropertyBin
+        );
+        assert_eq!(
+            LeagueFileKind::from_extension("txt"),
+            LeagueFileKind::Unknown
+        );
+    }
+}
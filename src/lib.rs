@@ -1,2 +1,3 @@
+pub mod league_file;
 pub mod streaming;
 pub mod wad;
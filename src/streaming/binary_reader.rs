@@ -9,6 +9,13 @@ pub struct BinaryReader<T: Read = File> {
     reader: BufReader<T>,
 }
 
+impl<T: Read> BinaryReader<T> {
+    pub fn new(reader: T) -> Self {
+        BinaryReader {
+            reader: BufReader::new(reader),
+        }
+    }
+}
 impl BinaryReader<Cursor<Vec<u8>>> {
     pub fn from_buffer(buffer: Cursor<Vec<u8>>) -> Self {
         BinaryReader {
@@ -17,12 +24,12 @@ impl BinaryReader<Cursor<Vec<u8>>> {
     }
 }
 impl BinaryReader<File> {
-    pub fn from_location(file_location: &Path) -> Self {
-        let file = File::open(file_location).unwrap();
+    pub fn from_location(file_location: &Path) -> io::Result<Self> {
+        let file = File::open(file_location)?;
 
-        BinaryReader {
+        Ok(BinaryReader {
             reader: BufReader::new(file),
-        }
+        })
     }
     pub fn from_file(file: File) -> Self {
         BinaryReader {
@@ -31,7 +38,7 @@ impl BinaryReader<File> {
     }
 }
 
-impl<T: Read + Seek> BinaryReader<T> {
+impl<T: Read> BinaryReader<T> {
     pub fn read_char(&mut self) -> io::Result<char> {
         match self.read_u8() {
             Ok(x) => Ok(x as char),
@@ -130,8 +137,12 @@ impl<T: Read + Seek> BinaryReader<T> {
         self.read_string(length)
     }
     pub fn read_padded_string(&mut self, length: usize) -> io::Result<String> {
-        let string = self.read_string(length)?;
-        Ok(string[0..string.find('\0').unwrap()].to_string())
+        let mut string = self.read_string(length)?;
+        if let Some(end) = string.find('\0') {
+            string.truncate(end);
+        }
+
+        Ok(string)
     }
     pub fn read_null_terminated_string(&mut self) -> io::Result<String> {
         let mut string = String::new();
@@ -148,11 +159,23 @@ impl<T: Read + Seek> BinaryReader<T> {
 
         Ok(string)
     }
+}
 
+impl<T: Read + Seek> BinaryReader<T> {
     pub fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
         self.reader.seek(position)
     }
-    pub fn position(&mut self) -> u64 {
-        return self.reader.seek(SeekFrom::Current(0)).unwrap();
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.reader.stream_position()
+    }
+
+    pub fn get_ref(&self) -> &T {
+        self.reader.get_ref()
+    }
+}
+
+impl<T: Read> Read for BinaryReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
     }
 }
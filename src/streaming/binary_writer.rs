@@ -10,15 +10,16 @@ pub struct BinaryWriter<T: Write = File> {
 }
 
 impl BinaryWriter<File> {
-    pub fn from_location(file_location: &Path) -> Self {
+    pub fn from_location(file_location: &Path) -> io::Result<Self> {
         let file = OpenOptions::new()
             .write(true)
             .create(true)
-            .open(file_location);
+            .truncate(true)
+            .open(file_location)?;
 
-        BinaryWriter {
-            writer: BufWriter::new(file.unwrap()),
-        }
+        Ok(BinaryWriter {
+            writer: BufWriter::new(file),
+        })
     }
 
     pub fn from_file(file: File) -> Self {
@@ -76,17 +77,19 @@ impl<T: Write + Seek> BinaryWriter<T> {
     }
 
     pub fn write_bytes(&mut self, to_write: Vec<u8>) -> io::Result<usize> {
-        self.writer.write(to_write.as_slice())
+        self.write_slice(&to_write)
+    }
+    pub fn write_slice(&mut self, to_write: &[u8]) -> io::Result<usize> {
+        self.writer.write_all(to_write)?;
+
+        Ok(to_write.len())
     }
     pub fn write_string(&mut self, to_write: &str) -> io::Result<usize> {
         self.writer.write(to_write.as_bytes())
     }
     pub fn write_padded_string(&mut self, to_write: &str, length: usize) -> io::Result<usize> {
         let pad_count = length - to_write.len();
-        let mut pad_buffer: Vec<u8> = Vec::with_capacity(pad_count);
-        for i in 0..pad_count {
-            pad_buffer.push(0);
-        }
+        let pad_buffer: Vec<u8> = vec![0; pad_count];
 
         self.write_string(to_write)?;
         self.write_bytes(pad_buffer)
@@ -103,8 +106,15 @@ impl<T: Write + Seek> BinaryWriter<T> {
     pub fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
         self.writer.seek(position)
     }
-    pub fn position(&mut self) -> u64 {
-        return self.writer.seek(SeekFrom::Current(0)).unwrap();
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.writer.stream_position()
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    pub fn into_inner(self) -> io::Result<T> {
+        self.writer.into_inner().map_err(|error| error.into_error())
     }
 }
 
@@ -168,12 +178,12 @@ impl BinaryWriterWriteable for f64 {
 }
 impl BinaryWriterWriteable for &[u8] {
     fn write<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<usize> {
-        writer.write_bytes(self.to_vec())
+        writer.write_slice(self)
     }
 }
 impl BinaryWriterWriteable for Vec<u8> {
     fn write<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<usize> {
-        writer.write_bytes(self.to_vec())
+        writer.write_slice(self)
     }
 }
 impl BinaryWriterWriteable for String {
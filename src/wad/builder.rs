@@ -0,0 +1,542 @@
+use getset::{CopyGetters, Getters};
+use std::{
+    collections::{btree_map, BTreeMap, HashMap},
+    convert::TryFrom,
+    fs::{self, File},
+    io::{Seek, Write},
+    path::Path,
+};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::streaming::binary_writer::BinaryWriter;
+
+use super::{
+    compression::compression_ratio, extract::parse_hashed_file_name, hash_path, CompressionPolicy,
+    Entry, EntryCompression, EntryDataFormat, EntrySizes, WadError, WadHeader,
+};
+
+/// Builds a WAD archive from a set of entries, v3.1 unless another version is chosen
+///
+/// Entries are compressed as they are added and written sorted by their path hash. Entries whose compressed
+/// data is identical share a single copy of it.
+#[derive(Default)]
+pub struct WadBuilder {
+    header: WadHeader,
+    entries: BTreeMap<u64, WadBuilderEntry>,
+}
+
+struct WadBuilderEntry {
+    data: Vec<u8>,
+    uncompressed_size: usize,
+    data_format: EntryDataFormat,
+    data_checksum: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters, CopyGetters)]
+pub struct WadWriteSummary {
+    /// The number of entries pointing at the data of another entry
+    #[getset(get_copy = "pub")]
+    pub(crate) duplicated_entries: usize,
+    /// The number of bytes which didn't have to be written thanks to duplicated entries
+    #[getset(get_copy = "pub")]
+    pub(crate) saved_bytes: u64,
+    /// The sizes of every written entry, keyed by path hash
+    #[getset(get = "pub")]
+    entries: BTreeMap<u64, EntrySizes>,
+}
+
+impl WadWriteSummary {
+    /// The total compressed size of the entries relative to their total uncompressed size
+    ///
+    /// Duplicated entries are counted every time, see [`WadWriteSummary::saved_bytes`] for what they save.
+    pub fn compression_ratio(&self) -> f64 {
+        let (compressed_size, uncompressed_size) =
+            self.entries
+                .values()
+                .fold((0, 0), |(compressed_size, uncompressed_size), sizes| {
+                    (
+                        compressed_size + sizes.compressed_size() as u64,
+                        uncompressed_size + sizes.uncompressed_size() as u64,
+                    )
+                });
+
+        compression_ratio(compressed_size, uncompressed_size)
+    }
+
+    pub(crate) fn add_entry(&mut self, entry: &Entry) {
+        self.entries.insert(entry.xxhash(), EntrySizes::of(entry));
+    }
+
+    /// Counts an entry pointing at the already written `data` of another entry
+    pub(crate) fn add_duplicated_data(&mut self, data: &[u8]) {
+        self.duplicated_entries += 1;
+        self.saved_bytes += data.len() as u64;
+    }
+}
+
+/// The distinct stored data written to an archive, with a value such as the offset it was written at
+///
+/// Identical data is found by its checksum, the data itself is compared in case of collisions.
+pub(crate) struct UniqueData<'a, T> {
+    data: HashMap<u64, Vec<(&'a [u8], T)>>,
+}
+
+impl<'a, T> Default for UniqueData<'a, T> {
+    fn default() -> Self {
+        Self {
+            data: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, T> UniqueData<'a, T> {
+    /// Returns the value of data identical to `data`, `checksum` being the XXH3 hash of `data`
+    pub(crate) fn find(&self, checksum: u64, data: &[u8]) -> Option<&T> {
+        self.data
+            .get(&checksum)?
+            .iter()
+            .find(|(candidate, _)| *candidate == data)
+            .map(|(_, value)| value)
+    }
+
+    pub(crate) fn insert(&mut self, checksum: u64, data: &'a [u8], value: T) {
+        self.data.entry(checksum).or_default().push((data, value));
+    }
+}
+
+impl WadBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder which writes archives of the given version
+    pub fn with_version(major: u8, minor: u8) -> Result<Self, WadError> {
+        Ok(Self {
+            header: WadHeader::new(major, minor)?,
+            entries: BTreeMap::new(),
+        })
+    }
+
+    /// Creates a builder containing every file in `directory` and its subdirectories, compressed as chosen by `policy`
+    ///
+    /// Files are added by their path relative to `directory`. Files named after a path hash, the way entries with
+    /// unknown paths are extracted (16 hex digits with an optional extension), are added with that hash instead.
+    pub fn from_directory(directory: &Path, policy: &CompressionPolicy) -> Result<Self, WadError> {
+        let mut builder = Self::new();
+        let mut directories = vec![(directory.to_path_buf(), String::new())];
+        while let Some((current, relative_directory)) = directories.pop() {
+            for entry in fs::read_dir(current)? {
+                let entry = entry?;
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                let relative_path = match relative_directory.is_empty() {
+                    true => file_name.clone(),
+                    false => format!("{}/{}", relative_directory, file_name),
+                };
+                if entry.file_type()?.is_dir() {
+                    directories.push((entry.path(), relative_path));
+                    continue;
+                }
+
+                let data = fs::read(entry.path())?;
+                match parse_hashed_file_name(&file_name) {
+                    Some(xxhash) => builder.add_entry_with_policy(xxhash, &data, policy)?,
+                    None => builder.add_path_entry_with_policy(&relative_path, &data, policy)?,
+                };
+            }
+        }
+
+        Ok(builder)
+    }
+
+    /// Compresses `data` using `data_format` and adds it as an entry with the given path hash
+    pub fn add_entry(
+        &mut self,
+        xxhash: u64,
+        data: &[u8],
+        data_format: EntryDataFormat,
+    ) -> Result<(), WadError> {
+        self.insert(xxhash, data, data_format, |data| {
+            compress_data(data, data_format)
+        })
+    }
+
+    /// Compresses `data` using `compression` and adds it as an entry with the given path hash
+    pub fn add_compressed_entry(
+        &mut self,
+        xxhash: u64,
+        data: &[u8],
+        compression: EntryCompression,
+    ) -> Result<(), WadError> {
+        self.insert(xxhash, data, compression.data_format(), |data| {
+            compression.compress(data)
+        })
+    }
+
+    /// Adds an entry with the given path hash compressed as chosen by `policy`, returning the chosen compression
+    ///
+    /// Only the data of the entry is known to the policy, see [`WadBuilder::add_path_entry_with_policy`].
+    pub fn add_entry_with_policy(
+        &mut self,
+        xxhash: u64,
+        data: &[u8],
+        policy: &CompressionPolicy,
+    ) -> Result<EntryCompression, WadError> {
+        let compression = policy.choose(None, data);
+        self.add_compressed_entry(xxhash, data, compression)?;
+
+        Ok(compression)
+    }
+
+    /// Adds an entry with the given path compressed as chosen by `policy`, returning the chosen compression
+    pub fn add_path_entry_with_policy(
+        &mut self,
+        path: &str,
+        data: &[u8],
+        policy: &CompressionPolicy,
+    ) -> Result<EntryCompression, WadError> {
+        let compression = policy.choose(Some(path), data);
+        self.add_compressed_entry(hash_path(path), data, compression)?;
+
+        Ok(compression)
+    }
+
+    fn insert(
+        &mut self,
+        xxhash: u64,
+        data: &[u8],
+        data_format: EntryDataFormat,
+        compress: impl FnOnce(&[u8]) -> Result<Vec<u8>, WadError>,
+    ) -> Result<(), WadError> {
+        let vacant_entry = match self.entries.entry(xxhash) {
+            btree_map::Entry::Occupied(_) => return Err(WadError::DuplicateEntry(xxhash)),
+            btree_map::Entry::Vacant(vacant_entry) => vacant_entry,
+        };
+
+        let stored_data = compress(data)?;
+        vacant_entry.insert(WadBuilderEntry {
+            data_checksum: xxh3_64(&stored_data),
+            data: stored_data,
+            uncompressed_size: data.len(),
+            data_format,
+        });
+
+        Ok(())
+    }
+
+    /// Same as [`WadBuilder::add_entry`] but hashes `path` to get the path hash of the entry, see [`hash_path`]
+    pub fn add_path_entry(
+        &mut self,
+        path: &str,
+        data: &[u8],
+        data_format: EntryDataFormat,
+    ) -> Result<(), WadError> {
+        self.add_entry(hash_path(path), data, data_format)
+    }
+
+    pub fn write_to_path(&self, path: &Path) -> Result<WadWriteSummary, WadError> {
+        let mut bw = BinaryWriter::from_file(File::create(path)?);
+
+        self.write(&mut bw)
+    }
+
+    pub fn write<W: Write + Seek>(
+        &self,
+        bw: &mut BinaryWriter<W>,
+    ) -> Result<WadWriteSummary, WadError> {
+        self.header.write(bw, self.entries.len() as u32)?;
+
+        let mut summary = WadWriteSummary::default();
+        let mut unique_data = UniqueData::default();
+        let mut layout = Vec::with_capacity(self.entries.len());
+        let mut end = self.header.toc_offset() as u64
+            + self.entries.len() as u64 * self.header.toc_entry_size() as u64;
+        for entry in self.entries.values() {
+            match unique_data.find(entry.data_checksum, &entry.data) {
+                Some(&data_offset) => {
+                    summary.add_duplicated_data(&entry.data);
+                    layout.push((data_offset, true));
+                }
+                None => {
+                    unique_data.insert(entry.data_checksum, &entry.data, end);
+                    layout.push((end, false));
+                    end += entry.data.len() as u64;
+                }
+            }
+        }
+
+        let checksum_kind = self.header.checksum_kind();
+        for ((&xxhash, entry), &(data_offset, is_duplicated)) in self.entries.iter().zip(&layout) {
+            let mut toc_entry = Entry::new(
+                xxhash,
+                u32::try_from(data_offset)
+                    .map_err(|_| WadError::DataOffsetOutOfRange(data_offset))?,
+                &entry.data,
+                entry.uncompressed_size,
+                entry.data_format,
+                checksum_kind,
+            )?;
+            toc_entry.is_duplicated = is_duplicated;
+            toc_entry.write(bw, checksum_kind)?;
+            summary.add_entry(&toc_entry);
+        }
+
+        for (entry, &(_, is_duplicated)) in self.entries.values().zip(&layout) {
+            if !is_duplicated {
+                bw.write_slice(&entry.data)?;
+            }
+        }
+        bw.flush()?;
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+impl WadBuilder {
+    /// Writes the WAD to a buffer
+    pub(crate) fn to_buffer(&self) -> Vec<u8> {
+        let mut bw = BinaryWriter::from_buffer(std::io::Cursor::new(Vec::new()));
+        self.write(&mut bw).unwrap();
+
+        bw.into_inner().unwrap().into_inner()
+    }
+
+    /// Writes the WAD and mounts it from a buffer
+    pub(crate) fn mount(&self) -> super::Wad<std::io::Cursor<Vec<u8>>> {
+        super::Wad::mount_from_buffer(self.to_buffer()).unwrap()
+    }
+}
+
+pub(crate) fn compress_data(
+    data: &[u8],
+    data_format: EntryDataFormat,
+) -> Result<Vec<u8>, WadError> {
+    match data_format {
+        EntryDataFormat::Raw | EntryDataFormat::FileRedirection => Ok(data.to_vec()),
+        EntryDataFormat::GZip => EntryCompression::GZip.compress(data),
+        EntryDataFormat::Zstd => EntryCompression::default().compress(data),
+        format => Err(WadError::UnsupportedEntryDataFormat(format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Cursor};
+
+    use xxhash_rust::xxh3::xxh3_64;
+
+    use crate::streaming::{binary_reader::BinaryReader, binary_writer::BinaryWriter};
+    use crate::wad::{
+        hash_path, CompressionPolicy, EntryCompression, EntryDataChecksum, EntryDataFormat, Wad,
+        WadBuilder, WadError,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let data = b"league toolkit builder data ".repeat(128);
+        let mut builder = WadBuilder::new();
+        builder.add_entry(3, &data, EntryDataFormat::Zstd).unwrap();
+        builder.add_entry(1, &data, EntryDataFormat::Raw).unwrap();
+        builder.add_entry(2, &data, EntryDataFormat::GZip).unwrap();
+        builder
+            .add_path_entry(
+                "DATA/Characters/Aatrox/Aatrox.bin",
+                b"PROP",
+                EntryDataFormat::Raw,
+            )
+            .unwrap();
+
+        let buffer = builder.to_buffer();
+        let mut wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer))).unwrap();
+
+        assert_eq!(wad.entries().len(), 4);
+        assert!(wad.entries()[&1].data_offset() < wad.entries()[&2].data_offset());
+        assert!(wad.entries()[&2].data_offset() < wad.entries()[&3].data_offset());
+        for xxhash in 1..=3 {
+            let raw_data = wad.load_entry_raw_data(xxhash).unwrap();
+            match wad.entries()[&xxhash].data_checksum() {
+                EntryDataChecksum::XxHash3(checksum) => {
+                    assert_eq!(checksum.as_slice(), xxh3_64(&raw_data).to_le_bytes())
+                }
+                _ => panic!("expected an XXH3 checksum"),
+            }
+            assert_eq!(wad.load_entry_data(xxhash).unwrap(), data);
+        }
+        assert_eq!(
+            wad.load_entry_data(xxhash_rust::xxh64::xxh64(
+                b"data/characters/aatrox/aatrox.bin",
+                0
+            ))
+            .unwrap(),
+            b"PROP"
+        );
+        assert!(wad
+            .entry_by_path("DATA\\Characters\\Aatrox\\Aatrox.bin")
+            .is_some());
+    }
+
+    #[test]
+    fn test_deduplication() {
+        let texture = b"repeated texture data ".repeat(64);
+        let mut builder = WadBuilder::new();
+        for xxhash in 1..=3 {
+            builder
+                .add_entry(xxhash, &texture, EntryDataFormat::Zstd)
+                .unwrap();
+        }
+        builder
+            .add_entry(4, &texture, EntryDataFormat::Raw)
+            .unwrap();
+
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        let summary = builder.write(&mut bw).unwrap();
+        let buffer = bw.into_inner().unwrap().into_inner();
+        let mut wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer.clone()))).unwrap();
+
+        let compressed_size = wad.entries()[&1].compressed_size() as u64;
+        assert_eq!(summary.duplicated_entries(), 2);
+        assert_eq!(summary.saved_bytes(), 2 * compressed_size);
+        assert_eq!(
+            buffer.len() as u64,
+            272 + 4 * 32 + compressed_size + texture.len() as u64
+        );
+
+        assert!(!wad.entries()[&1].is_duplicated());
+        for xxhash in 2..=3 {
+            let entry = &wad.entries()[&xxhash];
+            assert!(entry.is_duplicated());
+            assert_eq!(entry.data_offset(), wad.entries()[&1].data_offset());
+        }
+        assert!(!wad.entries()[&4].is_duplicated());
+        for xxhash in 1..=4 {
+            assert_eq!(wad.load_entry_data(xxhash).unwrap(), texture);
+        }
+    }
+
+    #[test]
+    fn test_versions() {
+        let data = b"versioned entry data ".repeat(16);
+        for &(major, minor, toc_offset) in &[(1, 1, 12), (2, 0, 104), (3, 0, 272), (3, 4, 272)] {
+            let mut builder = WadBuilder::with_version(major, minor).unwrap();
+            builder.add_entry(1, &data, EntryDataFormat::Zstd).unwrap();
+            builder.add_entry(2, &data, EntryDataFormat::Zstd).unwrap();
+
+            let buffer = builder.to_buffer();
+            let mut wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer))).unwrap();
+            assert_eq!((wad.header().major(), wad.header().minor()), (major, minor));
+            assert_eq!(wad.header().toc_offset(), toc_offset);
+            assert!(wad.entries()[&2].is_duplicated());
+            assert_eq!(wad.load_entry_data(2).unwrap(), data);
+            assert!(wad.verify().unwrap().is_valid());
+        }
+
+        assert!(matches!(
+            WadBuilder::with_version(2, 2),
+            Err(WadError::UnsupportedVersion(2, 2))
+        ));
+    }
+
+    #[test]
+    fn test_compression_policy() {
+        let bin = b"PROP league toolkit property data ".repeat(64);
+        let png = b"\x89PNG\r\n\x1a\n".repeat(64);
+        let policy = CompressionPolicy::new(EntryCompression::Zstd(19))
+            .with_extension("bin", EntryCompression::GZip)
+            .with_min_size(16);
+
+        let mut builder = WadBuilder::new();
+        let chosen = [
+            builder.add_path_entry_with_policy("data/test.bin", &bin, &policy),
+            builder.add_entry_with_policy(2, &bin, &policy),
+            builder.add_entry_with_policy(3, &png, &policy),
+            builder.add_entry_with_policy(4, b"small", &policy),
+        ];
+        assert_eq!(
+            chosen
+                .iter()
+                .map(|chosen| *chosen.as_ref().unwrap())
+                .collect::<Vec<_>>(),
+            [
+                EntryCompression::GZip,
+                EntryCompression::Zstd(19),
+                EntryCompression::Raw,
+                EntryCompression::Raw
+            ]
+        );
+
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        let summary = builder.write(&mut bw).unwrap();
+        let mut wad = Wad::mount_from_buffer(bw.into_inner().unwrap().into_inner()).unwrap();
+        assert_eq!(wad.entries()[&2].data_format(), EntryDataFormat::Zstd);
+        assert_eq!(wad.load_entry_data(2).unwrap(), bin);
+
+        assert_eq!(summary.entries().len(), 4);
+        assert!(summary.entries()[&2].compression_ratio() < 0.1);
+        assert_eq!(summary.entries()[&3].compression_ratio(), 1.0);
+        let (compressed_size, uncompressed_size) =
+            wad.entries().values().fold((0, 0), |sizes, entry| {
+                (
+                    sizes.0 + entry.compressed_size() as u64,
+                    sizes.1 + entry.uncompressed_size() as u64,
+                )
+            });
+        assert_eq!(
+            summary.compression_ratio(),
+            compressed_size as f64 / uncompressed_size as f64
+        );
+    }
+
+    #[test]
+    fn test_from_directory() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path();
+        fs::create_dir_all(root.join("DATA/Characters/Aatrox")).unwrap();
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(root.join("DATA/Characters/Aatrox/Aatrox.bin"), b"PROP").unwrap();
+        fs::write(root.join("00000000000000ff.tex"), b"TEX\0").unwrap();
+        fs::write(root.join("assets/0123456789ABCDEF"), b"unknown").unwrap();
+        // Not 16 hex digits, so hashed as a path
+        fs::write(root.join("assets/0123456789abcdef0.bin"), b"known").unwrap();
+
+        let builder = WadBuilder::from_directory(root, &CompressionPolicy::default()).unwrap();
+        let mut wad = Wad::mount_from_buffer(builder.to_buffer()).unwrap();
+        assert_eq!(wad.entries().len(), 4);
+        assert_eq!(
+            wad.load_entry_data(hash_path("data/characters/aatrox/aatrox.bin"))
+                .unwrap(),
+            b"PROP"
+        );
+        assert_eq!(wad.load_entry_data(0xFF).unwrap(), b"TEX\0");
+        assert_eq!(wad.load_entry_data(0x0123456789ABCDEF).unwrap(), b"unknown");
+        assert_eq!(
+            wad.load_entry_data(hash_path("assets/0123456789abcdef0.bin"))
+                .unwrap(),
+            b"known"
+        );
+
+        fs::write(root.join("00000000000000ff"), b"colliding").unwrap();
+        assert!(matches!(
+            WadBuilder::from_directory(root, &CompressionPolicy::default()),
+            Err(WadError::DuplicateEntry(0xFF))
+        ));
+    }
+
+    #[test]
+    fn test_empty() {
+        let buffer = WadBuilder::new().to_buffer();
+        let wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer))).unwrap();
+
+        assert!(wad.entries().is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_entry() {
+        let mut builder = WadBuilder::new();
+        builder.add_entry(1, b"a", EntryDataFormat::Raw).unwrap();
+
+        assert!(matches!(
+            builder.add_entry(1, b"b", EntryDataFormat::Raw),
+            Err(WadError::DuplicateEntry(1))
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+use flate2::{write::GzEncoder, Compression};
+use std::{collections::HashMap, io::Write};
+
+use crate::league_file::{identify, LeagueFileKind};
+
+use super::{EntryDataFormat, WadError};
+
+/// The compression applied to the data of an entry when it is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryCompression {
+    Raw,
+    GZip,
+    /// Zstandard with the given compression level, `0` picks the default level
+    Zstd(i32),
+}
+
+impl Default for EntryCompression {
+    fn default() -> Self {
+        EntryCompression::Zstd(0)
+    }
+}
+
+impl EntryCompression {
+    /// The format the data of an entry compressed this way is stored with
+    pub fn data_format(&self) -> EntryDataFormat {
+        match self {
+            EntryCompression::Raw => EntryDataFormat::Raw,
+            EntryCompression::GZip => EntryDataFormat::GZip,
+            EntryCompression::Zstd(_) => EntryDataFormat::Zstd,
+        }
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, WadError> {
+        match self {
+            EntryCompression::Raw => Ok(data.to_vec()),
+            EntryCompression::GZip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+
+                Ok(encoder.finish()?)
+            }
+            EntryCompression::Zstd(level) => Ok(zstd::stream::encode_all(data, *level)?),
+        }
+    }
+}
+
+/// Chooses the compression of each entry written to a WAD
+///
+/// The compression of an entry is chosen by the first of these that applies:
+/// 1. Entries smaller than the minimum size are stored raw
+/// 2. The rule for the extension of the path of the entry
+/// 3. The rule for the [`LeagueFileKind`] identified from the data of the entry
+/// 4. Formats which are compressed already, such as PNG and JPEG images or Wwise audio, are stored raw
+/// 5. The default compression
+#[derive(Debug, Clone)]
+pub struct CompressionPolicy {
+    default: EntryCompression,
+    extensions: HashMap<String, EntryCompression>,
+    kinds: HashMap<LeagueFileKind, EntryCompression>,
+    min_size: usize,
+    reuse_stored_data: bool,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self::new(EntryCompression::default())
+    }
+}
+
+impl CompressionPolicy {
+    /// Creates a policy compressing every entry with `default` that isn't covered by a more specific rule
+    pub fn new(default: EntryCompression) -> Self {
+        Self {
+            default,
+            extensions: HashMap::new(),
+            kinds: HashMap::new(),
+            min_size: 0,
+            reuse_stored_data: true,
+        }
+    }
+
+    /// Compresses entries whose path has `extension` with `compression`
+    pub fn with_extension(mut self, extension: &str, compression: EntryCompression) -> Self {
+        self.extensions
+            .insert(normalize_extension(extension), compression);
+        self
+    }
+
+    /// Compresses entries whose data is of `kind` with `compression`
+    pub fn with_kind(mut self, kind: LeagueFileKind, compression: EntryCompression) -> Self {
+        self.kinds.insert(kind, compression);
+        self
+    }
+
+    /// Stores entries smaller than `min_size` bytes raw, since compressing them rarely pays off
+    pub fn with_min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Whether the stored data of existing entries is kept as-is when it already has the chosen data format,
+    /// instead of being recompressed. Enabled by default.
+    pub fn with_reuse_stored_data(mut self, reuse_stored_data: bool) -> Self {
+        self.reuse_stored_data = reuse_stored_data;
+        self
+    }
+
+    pub fn reuse_stored_data(&self) -> bool {
+        self.reuse_stored_data
+    }
+
+    /// Chooses the compression of an entry from its path, if it is known, and its uncompressed data
+    pub fn choose(&self, path: Option<&str>, data: &[u8]) -> EntryCompression {
+        if data.len() < self.min_size {
+            return EntryCompression::Raw;
+        }
+
+        let extension = path
+            .and_then(|path| path.rsplit(['/', '\\']).next())
+            .and_then(|file_name| file_name.rsplit_once('.'))
+            .map(|(_, extension)| normalize_extension(extension));
+        if let Some(&compression) = extension.and_then(|extension| self.extensions.get(&extension))
+        {
+            return compression;
+        }
+
+        let kind = identify(data);
+        if let Some(&compression) = self.kinds.get(&kind) {
+            return compression;
+        }
+
+        match kind {
+            LeagueFileKind::Png
+            | LeagueFileKind::Jpeg
+            | LeagueFileKind::WwiseBank
+            | LeagueFileKind::WwisePackage => EntryCompression::Raw,
+            _ => self.default,
+        }
+    }
+}
+
+/// The compressed size relative to the uncompressed size, `1.0` if there is no data
+pub(crate) fn compression_ratio(compressed_size: u64, uncompressed_size: u64) -> f64 {
+    match uncompressed_size {
+        0 => 1.0,
+        _ => compressed_size as f64 / uncompressed_size as f64,
+    }
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::league_file::LeagueFileKind;
+    use crate::wad::{CompressionPolicy, EntryCompression, EntryDataFormat};
+
+    #[test]
+    fn test_choose() {
+        let policy = CompressionPolicy::new(EntryCompression::Zstd(3))
+            .with_extension(".TEX", EntryCompression::Zstd(19))
+            .with_kind(LeagueFileKind::PropertyBin, EntryCompression::GZip)
+            .with_kind(LeagueFileKind::Jpeg, EntryCompression::Zstd(1))
+            .with_min_size(8);
+
+        let bin = b"PROP\x01\0\0\0 property data";
+        assert_eq!(policy.choose(None, b"PROP"), EntryCompression::Raw);
+        assert_eq!(policy.choose(None, bin), EntryCompression::GZip);
+        // Extensions take priority over the kind of the data
+        assert_eq!(
+            policy.choose(Some("assets/characters/aatrox.tex"), bin),
+            EntryCompression::Zstd(19)
+        );
+        assert_eq!(
+            policy.choose(Some("data/aatrox.bin"), b"unknown data"),
+            EntryCompression::Zstd(3)
+        );
+        assert_eq!(
+            policy.choose(None, b"\x89PNG\r\n\x1a\n image data"),
+            EntryCompression::Raw
+        );
+        assert_eq!(
+            policy.choose(None, b"\xFF\xD8\xFF\xE0 image data"),
+            EntryCompression::Zstd(1)
+        );
+
+        assert_eq!(
+            EntryCompression::Zstd(19).data_format(),
+            EntryDataFormat::Zstd
+        );
+    }
+}
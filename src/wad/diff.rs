@@ -0,0 +1,280 @@
+use getset::{CopyGetters, Getters};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Seek},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    compression::compression_ratio, Entry, EntryDataChecksum, Wad, WadError, WadHashtable,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EntryDiffKind {
+    Added,
+    Removed,
+    Modified,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntrySizes {
+    #[getset(get_copy = "pub")]
+    compressed_size: i32,
+    #[getset(get_copy = "pub")]
+    uncompressed_size: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntryDiff {
+    #[getset(get_copy = "pub")]
+    kind: EntryDiffKind,
+    /// The path of the entry, if it is known by the hashtable passed to [`diff`]
+    #[getset(get = "pub")]
+    path: Option<String>,
+    /// The sizes of the entry in the old WAD, `None` if it was added
+    #[getset(get_copy = "pub")]
+    old_sizes: Option<EntrySizes>,
+    /// The sizes of the entry in the new WAD, `None` if it was removed
+    #[getset(get_copy = "pub")]
+    new_sizes: Option<EntrySizes>,
+}
+
+/// The differences between two WADs, keyed by path hash
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WadDiff {
+    #[getset(get = "pub")]
+    entries: BTreeMap<u64, EntryDiff>,
+}
+
+impl WadDiff {
+    /// Returns `true` if any entry was added, removed or modified
+    pub fn has_changes(&self) -> bool {
+        self.changes().next().is_some()
+    }
+
+    /// Returns the entries that were added, removed or modified
+    pub fn changes(&self) -> impl Iterator<Item = (u64, &EntryDiff)> {
+        self.entries
+            .iter()
+            .filter(|(_, diff)| diff.kind != EntryDiffKind::Unchanged)
+            .map(|(&xxhash, diff)| (xxhash, diff))
+    }
+
+    /// Returns the entries of the given kind
+    pub fn entries_of_kind(&self, kind: EntryDiffKind) -> impl Iterator<Item = (u64, &EntryDiff)> {
+        self.entries
+            .iter()
+            .filter(move |(_, diff)| diff.kind == kind)
+            .map(|(&xxhash, diff)| (xxhash, diff))
+    }
+}
+
+impl EntrySizes {
+    pub(crate) fn of(entry: &Entry) -> Self {
+        Self {
+            compressed_size: entry.compressed_size(),
+            uncompressed_size: entry.uncompressed_size(),
+        }
+    }
+
+    /// The compressed size relative to the uncompressed size, `1.0` for empty entries
+    pub fn compression_ratio(&self) -> f64 {
+        compression_ratio(self.compressed_size as u64, self.uncompressed_size as u64)
+    }
+}
+
+/// Compares the entries of `old` with the entries of `new`
+///
+/// Entries are compared by their checksum when both WADs store comparable checksums. Otherwise, or if the
+/// checksums differ, their data is compared, so that entries which were only recompressed count as unchanged.
+///
+/// Entries whose data can't be read or decoded, such as redirections or subchunked entries of a WAD without a
+/// loaded subchunk TOC, are only compared by their stored data and are reported as modified if it differs.
+pub fn diff<A: Read + Seek, B: Read + Seek>(
+    old: &mut Wad<A>,
+    new: &mut Wad<B>,
+    hashtable: Option<&WadHashtable>,
+) -> Result<WadDiff, WadError> {
+    let xxhashes: BTreeSet<u64> = old
+        .entries
+        .keys()
+        .chain(new.entries.keys())
+        .copied()
+        .collect();
+
+    let mut diff = WadDiff::default();
+    for xxhash in xxhashes {
+        let old_sizes = old.entries.get(&xxhash).map(EntrySizes::of);
+        let new_sizes = new.entries.get(&xxhash).map(EntrySizes::of);
+        let kind = match (old_sizes, new_sizes) {
+            (None, _) => EntryDiffKind::Added,
+            (_, None) => EntryDiffKind::Removed,
+            (Some(_), Some(_)) if is_unchanged(old, new, xxhash) => EntryDiffKind::Unchanged,
+            (Some(_), Some(_)) => EntryDiffKind::Modified,
+        };
+
+        diff.entries.insert(
+            xxhash,
+            EntryDiff {
+                kind,
+                path: hashtable
+                    .and_then(|hashtable| hashtable.resolve(xxhash))
+                    .map(str::to_string),
+                old_sizes,
+                new_sizes,
+            },
+        );
+    }
+
+    Ok(diff)
+}
+
+fn is_unchanged<A: Read + Seek, B: Read + Seek>(
+    old: &mut Wad<A>,
+    new: &mut Wad<B>,
+    xxhash: u64,
+) -> bool {
+    let old_entry = &old.entries[&xxhash];
+    let new_entry = &new.entries[&xxhash];
+    if old_entry.uncompressed_size() != new_entry.uncompressed_size() {
+        return false;
+    }
+
+    let same_format = old_entry.data_format() == new_entry.data_format();
+    let old_checksum = old_entry.data_checksum();
+    if same_format
+        && *old_checksum != EntryDataChecksum::None
+        && old_checksum == new_entry.data_checksum()
+    {
+        return true;
+    }
+
+    // A single unreadable entry counts as modified instead of failing the whole diff
+    let (old_raw_data, new_raw_data) = match (
+        old.load_entry_raw_data(xxhash),
+        new.load_entry_raw_data(xxhash),
+    ) {
+        (Ok(old_raw_data), Ok(new_raw_data)) => (old_raw_data, new_raw_data),
+        _ => return false,
+    };
+    if same_format && old_raw_data == new_raw_data {
+        return true;
+    }
+
+    match (
+        old.decompress_entry_data(&old.entries[&xxhash], &old_raw_data),
+        new.decompress_entry_data(&new.entries[&xxhash], &new_raw_data),
+    ) {
+        (Ok(old_data), Ok(new_data)) => old_data == new_data,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::wad::{
+        diff, hash_path, EntryDataFormat, EntryDiffKind, Wad, WadBuilder, WadHashtable,
+    };
+
+    fn create_wad(entries: &[(u64, &[u8], EntryDataFormat)]) -> Wad<Cursor<Vec<u8>>> {
+        let mut builder = WadBuilder::new();
+        for (xxhash, data, format) in entries {
+            builder.add_entry(*xxhash, data, *format).unwrap();
+        }
+
+        builder.mount()
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut old = create_wad(&[
+            (1, b"removed", EntryDataFormat::Raw),
+            (2, b"unchanged", EntryDataFormat::Zstd),
+            (3, b"recompressed", EntryDataFormat::GZip),
+            (4, b"modified", EntryDataFormat::Zstd),
+            (5, b"resized", EntryDataFormat::Raw),
+        ]);
+        let mut new = create_wad(&[
+            (2, b"unchanged", EntryDataFormat::Zstd),
+            (3, b"recompressed", EntryDataFormat::Zstd),
+            (4, b"MODIFIED", EntryDataFormat::Zstd),
+            (5, b"resized entry", EntryDataFormat::Raw),
+            (6, b"added", EntryDataFormat::Raw),
+        ]);
+        let mut hashtable = WadHashtable::new();
+        hashtable.insert(6, "data/added.bin".to_string());
+
+        let wad_diff = diff(&mut old, &mut new, Some(&hashtable)).unwrap();
+        let kinds: Vec<(u64, EntryDiffKind)> = wad_diff
+            .entries()
+            .iter()
+            .map(|(&xxhash, entry_diff)| (xxhash, entry_diff.kind()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, EntryDiffKind::Removed),
+                (2, EntryDiffKind::Unchanged),
+                (3, EntryDiffKind::Unchanged),
+                (4, EntryDiffKind::Modified),
+                (5, EntryDiffKind::Modified),
+                (6, EntryDiffKind::Added),
+            ]
+        );
+        assert!(wad_diff.has_changes());
+        assert_eq!(wad_diff.changes().count(), 4);
+
+        let added = &wad_diff.entries()[&6];
+        assert_eq!(added.path().as_deref(), Some("data/added.bin"));
+        assert_eq!(added.old_sizes(), None);
+        assert_eq!(added.new_sizes().unwrap().uncompressed_size(), 5);
+        assert_eq!(wad_diff.entries()[&1].new_sizes(), None);
+
+        let mut same = create_wad(&[(hash_path("data/a.bin"), b"a", EntryDataFormat::Raw)]);
+        let mut other_same = create_wad(&[(hash_path("data/a.bin"), b"a", EntryDataFormat::Raw)]);
+        assert!(!diff(&mut same, &mut other_same, None)
+            .unwrap()
+            .has_changes());
+    }
+
+    #[test]
+    fn test_diff_undecodable() {
+        let mut old = create_wad(&[
+            (1, b"\x05\0\0\0old/a", EntryDataFormat::FileRedirection),
+            (2, b"\x05\0\0\0old/b", EntryDataFormat::FileRedirection),
+        ]);
+        let mut new = create_wad(&[
+            (1, b"\x05\0\0\0new/a", EntryDataFormat::FileRedirection),
+            (2, b"\x05\0\0\0old/b", EntryDataFormat::FileRedirection),
+        ]);
+
+        let wad_diff = diff(&mut old, &mut new, None).unwrap();
+        assert_eq!(wad_diff.entries()[&1].kind(), EntryDiffKind::Modified);
+        assert_eq!(wad_diff.entries()[&2].kind(), EntryDiffKind::Unchanged);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_diff() {
+        let mut old = create_wad(&[(1, b"old", EntryDataFormat::Raw)]);
+        let mut new = create_wad(&[(2, b"new", EntryDataFormat::Raw)]);
+        let wad_diff = diff(&mut old, &mut new, None).unwrap();
+
+        let json = serde_json::to_string(&wad_diff).unwrap();
+        assert!(json.contains(r#""kind":"removed""#));
+        assert_eq!(
+            serde_json::from_str::<crate::wad::WadDiff>(&json).unwrap(),
+            wad_diff
+        );
+    }
+}
@@ -0,0 +1,780 @@
+use std::{
+    collections::{btree_map, hash_map, BTreeMap, HashMap},
+    convert::TryFrom,
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::streaming::binary_writer::BinaryWriter;
+
+use super::{
+    builder::{compress_data, UniqueData},
+    write_through_temporary_file, CompressionPolicy, Entry, EntryDataChecksum, EntryDataFormat,
+    Wad, WadError, WadHashtable, WadHeader, WadWriteSummary,
+};
+
+/// Collects changes to the entries of a [`Wad`] and writes them back
+///
+/// Nothing is written until the editor is saved, see [`WadEditor::save_appending`] and [`WadEditor::save_compacted`].
+pub struct WadEditor<'a, R: Read + Seek> {
+    wad: &'a mut Wad<R>,
+    /// The entries of the edited WAD, keyed by their new path hash
+    entries: BTreeMap<u64, EditedEntry>,
+}
+
+enum EditedEntry {
+    /// The entry of the original WAD with the given path hash, its stored data is reused as-is
+    Original(u64),
+    New {
+        data: Vec<u8>,
+        uncompressed_size: usize,
+        data_format: EntryDataFormat,
+    },
+}
+
+impl<R: Read + Seek> Wad<R> {
+    /// Starts editing this WAD
+    pub fn edit(&mut self) -> WadEditor<'_, R> {
+        WadEditor::new(self)
+    }
+
+    /// Same as [`Wad::convert`], writing to the file at `path`
+    ///
+    /// The archive is written to a temporary file next to `path` which then replaces it, so `path` may be the file
+    /// this WAD was mounted from.
+    pub fn convert_to_path(
+        &mut self,
+        path: &Path,
+        major: u8,
+        minor: u8,
+    ) -> Result<WadWriteSummary, WadError> {
+        write_through_temporary_file(path, |bw| self.convert(bw, major, minor))
+    }
+
+    /// Writes this WAD to `bw` as the given version, re-encoding the checksums of its entries as needed
+    ///
+    /// The archive is written compacted and unsigned, see [`WadEditor::save_compacted_as`].
+    pub fn convert<W: Write + Seek>(
+        &mut self,
+        bw: &mut BinaryWriter<W>,
+        major: u8,
+        minor: u8,
+    ) -> Result<WadWriteSummary, WadError> {
+        self.edit().save_compacted_as(bw, major, minor)
+    }
+}
+
+impl EditedEntry {
+    fn new(data: &[u8], data_format: EntryDataFormat) -> Result<Self, WadError> {
+        Ok(EditedEntry::New {
+            data: compress_data(data, data_format)?,
+            uncompressed_size: data.len(),
+            data_format,
+        })
+    }
+}
+
+impl<'a, R: Read + Seek> WadEditor<'a, R> {
+    pub fn new(wad: &'a mut Wad<R>) -> Self {
+        let entries = wad
+            .entries
+            .keys()
+            .map(|&xxhash| (xxhash, EditedEntry::Original(xxhash)))
+            .collect();
+
+        Self { wad, entries }
+    }
+
+    /// Compresses `data` using `data_format` and adds it as a new entry with the given path hash
+    pub fn insert(
+        &mut self,
+        xxhash: u64,
+        data: &[u8],
+        data_format: EntryDataFormat,
+    ) -> Result<(), WadError> {
+        match self.entries.entry(xxhash) {
+            btree_map::Entry::Occupied(_) => Err(WadError::DuplicateEntry(xxhash)),
+            btree_map::Entry::Vacant(vacant_entry) => {
+                vacant_entry.insert(EditedEntry::new(data, data_format)?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Replaces the data of the entry with the given path hash
+    pub fn replace(
+        &mut self,
+        xxhash: u64,
+        data: &[u8],
+        data_format: EntryDataFormat,
+    ) -> Result<(), WadError> {
+        let entry = self
+            .entries
+            .get_mut(&xxhash)
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+        *entry = EditedEntry::new(data, data_format)?;
+
+        Ok(())
+    }
+
+    /// Recompresses the untouched entries as chosen by `policy`, resolving their paths through `hashtable`
+    ///
+    /// If the policy reuses stored data, entries which are already stored with the chosen data format are left
+    /// as they are. Inserted or replaced entries keep the format they were given. Redirections and entries
+    /// split into subchunks are never recompressed, since the subchunk TOC describes their stored data.
+    pub fn recompress(
+        &mut self,
+        policy: &CompressionPolicy,
+        hashtable: Option<&WadHashtable>,
+    ) -> Result<(), WadError> {
+        for (&xxhash, edited_entry) in self.entries.iter_mut() {
+            let (original_xxhash, data_format) = match edited_entry {
+                EditedEntry::Original(original_xxhash) => (
+                    *original_xxhash,
+                    self.wad.entries[original_xxhash].data_format,
+                ),
+                EditedEntry::New { .. } => continue,
+            };
+            if matches!(
+                data_format,
+                EntryDataFormat::FileRedirection | EntryDataFormat::ZstdMulti
+            ) {
+                continue;
+            }
+
+            let data = self.wad.load_entry_data(original_xxhash)?;
+            let compression = policy.choose(
+                hashtable.and_then(|hashtable| hashtable.resolve(xxhash)),
+                &data,
+            );
+            if policy.reuse_stored_data() && data_format == compression.data_format() {
+                continue;
+            }
+
+            *edited_entry = EditedEntry::New {
+                data: compression.compress(&data)?,
+                uncompressed_size: data.len(),
+                data_format: compression.data_format(),
+            };
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, xxhash: u64) -> Result<(), WadError> {
+        self.entries
+            .remove(&xxhash)
+            .map(|_| ())
+            .ok_or(WadError::EntryNotFound(xxhash))
+    }
+
+    /// Moves the entry with the path hash `from` to the path hash `to`
+    pub fn rename(&mut self, from: u64, to: u64) -> Result<(), WadError> {
+        if self.entries.contains_key(&to) {
+            return Err(WadError::DuplicateEntry(to));
+        }
+        let entry = self
+            .entries
+            .remove(&from)
+            .ok_or(WadError::EntryNotFound(from))?;
+        self.entries.insert(to, entry);
+
+        Ok(())
+    }
+
+    pub fn contains(&self, xxhash: u64) -> bool {
+        self.entries.contains_key(&xxhash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Same as [`WadEditor::save_appending`], opening the WAD at `path` for writing
+    pub fn save_appending_to_path(&mut self, path: &Path) -> Result<(), WadError> {
+        let mut bw = BinaryWriter::from_file(OpenOptions::new().write(true).open(path)?);
+
+        self.save_appending(&mut bw)
+    }
+
+    /// Writes the changes into the archive the WAD was mounted from, `bw` has to write to that same archive
+    ///
+    /// New data is appended to the end of the archive, once for entries with identical data, and the TOC is
+    /// rewritten in place. The data of entries which would be overwritten by a grown TOC is moved to the end as well. The space taken up by removed or replaced
+    /// entries isn't reclaimed, see [`WadEditor::save_compacted`] for that.
+    ///
+    /// Only v3 archives can be edited in place. The entries of the WAD are updated once the changes are written.
+    pub fn save_appending<W: Write + Seek>(
+        &mut self,
+        bw: &mut BinaryWriter<W>,
+    ) -> Result<(), WadError> {
+        let header = &self.wad.header;
+        if header.major() != 3 {
+            return Err(WadError::UnsupportedVersion(header.major(), header.minor()));
+        }
+        let checksum_kind = header.checksum_kind();
+        let entry_count_offset = header.entry_count_offset();
+        let toc_offset = header.toc_offset() as u64;
+
+        let toc_end = toc_offset + self.entries.len() as u64 * header.toc_entry_size() as u64;
+        let mut end = self.wad.source.seek(SeekFrom::End(0))?.max(toc_end);
+        bw.seek(SeekFrom::Start(end))?;
+
+        let mut toc = Vec::with_capacity(self.entries.len());
+        // Keyed by the range of the data, since an empty entry can share its offset with another entry
+        let mut moved_offsets = HashMap::new();
+        let mut unique_data = UniqueData::default();
+        for (&xxhash, edited_entry) in &self.entries {
+            let entry = match edited_entry {
+                EditedEntry::Original(original_xxhash) => {
+                    let mut entry = self.wad.entries[original_xxhash].clone();
+                    entry.xxhash = xxhash;
+                    if (entry.data_offset as u64) < toc_end {
+                        entry.data_offset = match moved_offsets.entry(entry.data_range()) {
+                            hash_map::Entry::Occupied(moved_offset) => *moved_offset.get(),
+                            hash_map::Entry::Vacant(moved_offset) => {
+                                let data = Wad::read_raw_data(&mut self.wad.source, &entry)?;
+                                *moved_offset.insert(append_data(bw, &mut end, &data)?)
+                            }
+                        };
+                    }
+
+                    entry
+                }
+                EditedEntry::New {
+                    data,
+                    uncompressed_size,
+                    data_format,
+                } => {
+                    let (data_offset, is_duplicated) =
+                        append_unique_data(bw, &mut end, &mut unique_data, data)?;
+                    let mut entry = Entry::new(
+                        xxhash,
+                        data_offset,
+                        data,
+                        *uncompressed_size,
+                        *data_format,
+                        checksum_kind,
+                    )?;
+                    entry.is_duplicated = is_duplicated;
+
+                    entry
+                }
+            };
+
+            toc.push(entry);
+        }
+
+        bw.seek(SeekFrom::Start(entry_count_offset))?;
+        bw.write_u32(toc.len() as u32)?;
+        bw.seek(SeekFrom::Start(toc_offset))?;
+        for entry in &toc {
+            entry.write(bw, checksum_kind)?;
+        }
+        bw.flush()?;
+
+        self.wad.toc_order = toc.iter().map(|entry| entry.xxhash).collect();
+        self.wad.entries = toc.into_iter().map(|entry| (entry.xxhash, entry)).collect();
+        self.entries = self
+            .wad
+            .entries
+            .keys()
+            .map(|&xxhash| (xxhash, EditedEntry::Original(xxhash)))
+            .collect();
+
+        Ok(())
+    }
+
+    /// Same as [`WadEditor::save_compacted`], writing to the file at `path`
+    ///
+    /// The archive is written to a temporary file next to `path` which then replaces it, so `path` may be the file
+    /// the WAD was mounted from.
+    pub fn save_compacted_to_path(&mut self, path: &Path) -> Result<WadWriteSummary, WadError> {
+        write_through_temporary_file(path, |bw| self.save_compacted(bw))
+    }
+
+    /// Writes the edited WAD to `bw` as a new archive of the same version without any unused space
+    ///
+    /// The data of untouched entries is copied without being recompressed. Inserted or replaced entries with
+    /// identical data share a single copy of it. `bw` must not write to the archive the WAD is read from.
+    pub fn save_compacted<W: Write + Seek>(
+        &mut self,
+        bw: &mut BinaryWriter<W>,
+    ) -> Result<WadWriteSummary, WadError> {
+        self.save_compacted_as(bw, self.wad.header.major(), self.wad.header.minor())
+    }
+
+    /// Same as [`WadEditor::save_compacted`] but writes the archive as the given version
+    ///
+    /// The checksums of copied entries are recomputed if the version stores a different kind of checksum.
+    pub fn save_compacted_as<W: Write + Seek>(
+        &mut self,
+        bw: &mut BinaryWriter<W>,
+        major: u8,
+        minor: u8,
+    ) -> Result<WadWriteSummary, WadError> {
+        // The signature of the original archive doesn't match the edited one
+        let header = WadHeader::new(major, minor)?;
+        let checksum_kind = header.checksum_kind();
+        let toc_offset = header.toc_offset() as u64;
+        let toc_size = self.entries.len() as u64 * header.toc_entry_size() as u64;
+
+        header.write(bw, self.entries.len() as u32)?;
+        // The TOC is written once the offsets of the data are known
+        bw.write_slice(&vec![0; toc_size as usize])?;
+
+        let mut end = toc_offset + toc_size;
+        let mut summary = WadWriteSummary::default();
+        let mut toc = Vec::with_capacity(self.entries.len());
+        let mut copied_data: HashMap<(u32, i32), (u32, EntryDataChecksum)> = HashMap::new();
+        let mut unique_data = UniqueData::default();
+        for (&xxhash, edited_entry) in &self.entries {
+            let entry = match edited_entry {
+                EditedEntry::Original(original_xxhash) => {
+                    let mut entry = self.wad.entries[original_xxhash].clone();
+                    entry.xxhash = xxhash;
+
+                    // Entries sharing their data with another entry keep sharing it
+                    let (data_offset, data_checksum) = match copied_data.entry(entry.data_range()) {
+                        hash_map::Entry::Occupied(copied_data) => {
+                            summary.duplicated_entries += 1;
+                            summary.saved_bytes += entry.compressed_size as u64;
+                            copied_data.get().clone()
+                        }
+                        hash_map::Entry::Vacant(copied_data) => {
+                            let data = Wad::read_raw_data(&mut self.wad.source, &entry)?;
+                            let data_checksum = match entry.data_checksum.kind() == checksum_kind {
+                                true => entry.data_checksum.clone(),
+                                false => EntryDataChecksum::compute(checksum_kind, &data),
+                            };
+
+                            copied_data
+                                .insert((append_data(bw, &mut end, &data)?, data_checksum))
+                                .clone()
+                        }
+                    };
+                    entry.data_offset = data_offset;
+                    entry.data_checksum = data_checksum;
+
+                    entry
+                }
+                EditedEntry::New {
+                    data,
+                    uncompressed_size,
+                    data_format,
+                } => {
+                    let (data_offset, is_duplicated) =
+                        append_unique_data(bw, &mut end, &mut unique_data, data)?;
+                    if is_duplicated {
+                        summary.add_duplicated_data(data);
+                    }
+                    let mut entry = Entry::new(
+                        xxhash,
+                        data_offset,
+                        data,
+                        *uncompressed_size,
+                        *data_format,
+                        checksum_kind,
+                    )?;
+                    entry.is_duplicated = is_duplicated;
+
+                    entry
+                }
+            };
+
+            toc.push(entry);
+        }
+
+        bw.seek(SeekFrom::Start(toc_offset))?;
+        for entry in &toc {
+            entry.write(bw, checksum_kind)?;
+            summary.add_entry(entry);
+        }
+        bw.seek(SeekFrom::Start(end))?;
+        bw.flush()?;
+
+        Ok(summary)
+    }
+}
+
+/// Writes `data` at `end` and moves `end` past it, returning the offset the data was written at
+fn append_data<W: Write + Seek>(
+    bw: &mut BinaryWriter<W>,
+    end: &mut u64,
+    data: &[u8],
+) -> Result<u32, WadError> {
+    let data_offset = u32::try_from(*end).map_err(|_| WadError::DataOffsetOutOfRange(*end))?;
+    bw.write_slice(data)?;
+    *end += data.len() as u64;
+
+    Ok(data_offset)
+}
+
+/// Same as [`append_data`] unless identical data was appended before, in which case that data is shared
+///
+/// Returns the offset of the data and whether it is shared with another entry.
+fn append_unique_data<'d, W: Write + Seek>(
+    bw: &mut BinaryWriter<W>,
+    end: &mut u64,
+    unique_data: &mut UniqueData<'d, u32>,
+    data: &'d [u8],
+) -> Result<(u32, bool), WadError> {
+    let checksum = xxh3_64(data);
+    if let Some(&data_offset) = unique_data.find(checksum, data) {
+        return Ok((data_offset, true));
+    }
+
+    let data_offset = append_data(bw, end, data)?;
+    unique_data.insert(checksum, data, data_offset);
+
+    Ok((data_offset, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Cursor};
+
+    use crate::streaming::binary_writer::BinaryWriter;
+    use crate::wad::{
+        CompressionPolicy, EntryCompression, EntryDataChecksumKind, EntryDataFormat, Wad,
+        WadBuilder, WadError, WadHashtable,
+    };
+
+    fn build_wad() -> Vec<u8> {
+        let mut builder = WadBuilder::new();
+        builder
+            .add_entry(1, b"first", EntryDataFormat::Raw)
+            .unwrap();
+        builder
+            .add_entry(2, b"second", EntryDataFormat::Zstd)
+            .unwrap();
+        builder
+            .add_entry(3, b"third", EntryDataFormat::GZip)
+            .unwrap();
+        builder
+            .add_entry(4, &b"untouched ".repeat(32), EntryDataFormat::GZip)
+            .unwrap();
+
+        builder.to_buffer()
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut wad = Wad::mount_from_buffer(build_wad()).unwrap();
+        let mut editor = wad.edit();
+
+        assert!(matches!(
+            editor.insert(1, b"", EntryDataFormat::Raw),
+            Err(WadError::DuplicateEntry(1))
+        ));
+        assert!(matches!(
+            editor.replace(5, b"", EntryDataFormat::Raw),
+            Err(WadError::EntryNotFound(5))
+        ));
+        assert!(matches!(editor.remove(5), Err(WadError::EntryNotFound(5))));
+        assert!(matches!(
+            editor.rename(1, 2),
+            Err(WadError::DuplicateEntry(2))
+        ));
+        assert!(matches!(
+            editor.rename(5, 6),
+            Err(WadError::EntryNotFound(5))
+        ));
+
+        editor.rename(1, 10).unwrap();
+        editor.remove(2).unwrap();
+        editor
+            .replace(3, b"replaced", EntryDataFormat::Zstd)
+            .unwrap();
+        editor
+            .insert(5, b"inserted", EntryDataFormat::GZip)
+            .unwrap();
+        assert_eq!(editor.len(), 4);
+        assert!(editor.contains(10) && !editor.contains(1));
+    }
+
+    #[test]
+    fn test_save_compacted() {
+        let buffer = build_wad();
+        let mut wad = Wad::mount_from_buffer(buffer.clone()).unwrap();
+        let mut editor = wad.edit();
+        editor.rename(1, 10).unwrap();
+        editor.remove(2).unwrap();
+        editor
+            .replace(3, b"replaced", EntryDataFormat::Zstd)
+            .unwrap();
+        editor
+            .insert(5, b"inserted", EntryDataFormat::GZip)
+            .unwrap();
+
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        editor.save_compacted(&mut bw).unwrap();
+        let edited_buffer = bw.into_inner().unwrap().into_inner();
+        let mut edited_wad = Wad::mount_from_buffer(edited_buffer.as_slice()).unwrap();
+
+        assert_eq!(edited_wad.entries().len(), 4);
+        assert_eq!(edited_wad.entry_data(10).unwrap(), b"first");
+        assert!(edited_wad.entry_data(2).is_err());
+        assert_eq!(edited_wad.entry_data(3).unwrap(), b"replaced");
+        assert_eq!(edited_wad.entry_data(5).unwrap(), b"inserted");
+        assert!(edited_wad.verify().unwrap().is_valid());
+
+        // Untouched entries are copied as they are stored
+        let original_wad = Wad::mount_from_buffer(buffer).unwrap();
+        assert_eq!(
+            edited_wad.entry_raw_data(4).unwrap(),
+            original_wad.entry_raw_data(4).unwrap()
+        );
+        assert_eq!(
+            edited_buffer.len(),
+            272 + 4 * 32
+                + [10, 3, 4, 5]
+                    .iter()
+                    .map(|xxhash| edited_wad.entries()[xxhash].compressed_size() as usize)
+                    .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_recompress() {
+        let buffer = build_wad();
+        let mut wad = Wad::mount_from_buffer(buffer.as_slice()).unwrap();
+        let mut hashtable = WadHashtable::new();
+        hashtable.insert(4, "data/untouched.txt".to_string());
+
+        let mut editor = wad.edit();
+        editor
+            .replace(3, b"replaced", EntryDataFormat::GZip)
+            .unwrap();
+        editor
+            .recompress(
+                &CompressionPolicy::new(EntryCompression::Zstd(1))
+                    .with_extension("txt", EntryCompression::Zstd(19)),
+                Some(&hashtable),
+            )
+            .unwrap();
+
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        let summary = editor.save_compacted(&mut bw).unwrap();
+        let edited_buffer = bw.into_inner().unwrap().into_inner();
+        let edited_wad = Wad::mount_from_buffer(edited_buffer.as_slice()).unwrap();
+
+        // The Zstd entry is reused as it is stored, the replaced entry keeps its format
+        let original_wad = Wad::mount_from_buffer(buffer).unwrap();
+        assert_eq!(
+            edited_wad.entry_raw_data(2).unwrap(),
+            original_wad.entry_raw_data(2).unwrap()
+        );
+        for &(xxhash, data_format) in &[
+            (1, EntryDataFormat::Zstd),
+            (3, EntryDataFormat::GZip),
+            (4, EntryDataFormat::Zstd),
+        ] {
+            assert_eq!(edited_wad.entries()[&xxhash].data_format(), data_format);
+        }
+        assert_eq!(edited_wad.entry_data(4).unwrap(), b"untouched ".repeat(32));
+        assert_eq!(summary.entries().len(), 4);
+        assert!(summary.entries()[&4].compression_ratio() < 1.0);
+    }
+
+    #[test]
+    fn test_convert() {
+        let buffer = build_wad();
+        let mut wad = Wad::mount_from_buffer(buffer.as_slice()).unwrap();
+
+        for &(major, minor, checksum_kind) in &[
+            (1, 0, EntryDataChecksumKind::None),
+            (2, 1, EntryDataChecksumKind::Sha256),
+            (3, 0, EntryDataChecksumKind::Sha256),
+            (3, 4, EntryDataChecksumKind::XxHash3),
+        ] {
+            let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+            wad.convert(&mut bw, major, minor).unwrap();
+            let converted_buffer = bw.into_inner().unwrap().into_inner();
+            let mut converted_wad = Wad::mount_from_buffer(converted_buffer.as_slice()).unwrap();
+
+            assert_eq!(converted_buffer[2..4], [major, minor]);
+            assert_eq!(converted_wad.header().checksum_kind(), checksum_kind);
+            for (xxhash, entry) in converted_wad.entries() {
+                assert_eq!(entry.data_checksum().kind(), checksum_kind);
+                assert_eq!(
+                    converted_wad.entry_raw_data(*xxhash).unwrap(),
+                    wad.entry_raw_data(*xxhash).unwrap()
+                );
+            }
+            assert_eq!(
+                converted_wad.load_entry_data(4).unwrap(),
+                b"untouched ".repeat(32)
+            );
+            assert!(converted_wad.verify().unwrap().is_valid());
+
+            // Converting back gives the same archive
+            let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+            converted_wad.convert(&mut bw, 3, 1).unwrap();
+            assert_eq!(bw.into_inner().unwrap().into_inner(), buffer);
+        }
+
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        assert!(matches!(
+            wad.convert(&mut bw, 4, 0),
+            Err(WadError::UnsupportedVersion(4, 0))
+        ));
+    }
+
+    #[test]
+    fn test_save_empty_entry() {
+        // The builder stores the empty entry at the same offset as the data of the next entry
+        let mut builder = WadBuilder::new();
+        builder.add_entry(1, b"", EntryDataFormat::Raw).unwrap();
+        builder
+            .add_entry(2, b"payload data", EntryDataFormat::Raw)
+            .unwrap();
+
+        let mut wad = builder.mount();
+        assert_eq!(
+            wad.entries()[&1].data_offset(),
+            wad.entries()[&2].data_offset()
+        );
+        for &(major, minor) in &[(3, 1), (2, 0)] {
+            let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+            wad.convert(&mut bw, major, minor).unwrap();
+            let mut converted_wad =
+                Wad::mount_from_buffer(bw.into_inner().unwrap().into_inner()).unwrap();
+            assert_eq!(converted_wad.load_entry_data(1).unwrap(), b"");
+            assert_eq!(converted_wad.load_entry_data(2).unwrap(), b"payload data");
+            assert!(converted_wad.verify().unwrap().is_valid());
+        }
+
+        // Growing the TOC moves both entries
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Test.wad.client");
+        fs::write(&path, builder.to_buffer()).unwrap();
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        let mut editor = wad.edit();
+        editor.insert(3, b"third", EntryDataFormat::Raw).unwrap();
+        editor.save_appending_to_path(&path).unwrap();
+
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"");
+        assert_eq!(wad.load_entry_data(2).unwrap(), b"payload data");
+        assert!(wad.verify().unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_save_appending() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Test.wad.client");
+        let buffer = build_wad();
+        fs::write(&path, &buffer).unwrap();
+
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        let mut editor = wad.edit();
+        editor
+            .replace(3, b"replaced", EntryDataFormat::Zstd)
+            .unwrap();
+        editor.remove(2).unwrap();
+        // Grow the TOC over the data of the first entries, the data of the other entries stays in place
+        for xxhash in 100..103 {
+            editor
+                .insert(xxhash, &xxhash.to_le_bytes(), EntryDataFormat::Raw)
+                .unwrap();
+        }
+        editor.save_appending_to_path(&path).unwrap();
+
+        assert_eq!(wad.entries().len(), 6);
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"first");
+        assert_eq!(wad.load_entry_data(3).unwrap(), b"replaced");
+
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        assert_eq!(wad.entries().len(), 6);
+        assert!(!wad.entries().contains_key(&2));
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"first");
+        assert_eq!(wad.load_entry_data(3).unwrap(), b"replaced");
+        assert_eq!(wad.load_entry_data(4).unwrap(), b"untouched ".repeat(32));
+        for xxhash in 100..103u64 {
+            assert_eq!(wad.load_entry_data(xxhash).unwrap(), xxhash.to_le_bytes());
+        }
+        assert!(wad.verify().unwrap().is_valid());
+
+        let edited_buffer = fs::read(&path).unwrap();
+        assert!(edited_buffer.len() > buffer.len());
+        assert_eq!(
+            edited_buffer[272 + 6 * 32..buffer.len()],
+            buffer[272 + 6 * 32..]
+        );
+    }
+
+    #[test]
+    fn test_save_duplicated_data() {
+        let texture = b"shared texture data ".repeat(64);
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Test.wad.client");
+        let buffer = build_wad();
+        fs::write(&path, &buffer).unwrap();
+
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        let mut editor = wad.edit();
+        for xxhash in 10..13 {
+            editor
+                .insert(xxhash, &texture, EntryDataFormat::Zstd)
+                .unwrap();
+        }
+        editor.replace(1, &texture, EntryDataFormat::Zstd).unwrap();
+
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        let summary = editor.save_compacted(&mut bw).unwrap();
+        let mut compacted_wad =
+            Wad::mount_from_buffer(bw.into_inner().unwrap().into_inner()).unwrap();
+        let compressed_size = compacted_wad.entries()[&1].compressed_size() as u64;
+        assert_eq!(summary.duplicated_entries(), 3);
+        assert_eq!(summary.saved_bytes(), 3 * compressed_size);
+        for xxhash in 10..13 {
+            assert_eq!(
+                compacted_wad.entries()[&xxhash].data_offset(),
+                compacted_wad.entries()[&1].data_offset()
+            );
+            assert!(compacted_wad.entries()[&xxhash].is_duplicated());
+            assert_eq!(compacted_wad.load_entry_data(xxhash).unwrap(), texture);
+        }
+
+        editor.save_appending_to_path(&path).unwrap();
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        for xxhash in 10..13 {
+            assert_eq!(
+                wad.entries()[&xxhash].data_offset(),
+                wad.entries()[&1].data_offset()
+            );
+            assert_eq!(wad.load_entry_data(xxhash).unwrap(), texture);
+        }
+        assert!(wad.verify().unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_save_compacted_to_mounted_path() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Test.wad.client");
+        fs::write(&path, build_wad()).unwrap();
+
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        let mut editor = wad.edit();
+        editor.remove(2).unwrap();
+        editor.save_compacted_to_path(&path).unwrap();
+
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        assert_eq!(wad.entries().len(), 3);
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"first");
+
+        wad.convert_to_path(&path, 2, 0).unwrap();
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        assert_eq!(wad.header().major(), 2);
+        assert_eq!(wad.load_entry_data(4).unwrap(), b"untouched ".repeat(32));
+        assert!(wad.verify().unwrap().is_valid());
+        assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+}
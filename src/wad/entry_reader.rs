@@ -0,0 +1,292 @@
+use flate2::read::GzDecoder;
+use std::{
+    io::{self, BufReader, Cursor, ErrorKind, Read, Seek, SeekFrom, Take},
+    iter::Copied,
+    slice,
+};
+
+use crate::streaming::binary_reader::BinaryReader;
+
+use super::{subchunk, Entry, EntryDataFormat, Wad, WadError, WadSubchunk};
+
+type StoredData<'a, R> = Take<&'a mut BinaryReader<R>>;
+
+/// Reads the data of a single entry straight from the source of its WAD
+///
+/// Compressed entries are decompressed as they are read, so only the part of the entry
+/// which is actually consumed gets decompressed. Raw entries can be seeked freely, compressed ones only forward.
+pub struct EntryReader<'a, R: Read + Seek> {
+    decoder: EntryDecoder<'a, R>,
+    xxhash: u64,
+    position: u64,
+    size: u64,
+}
+
+enum EntryDecoder<'a, R: Read + Seek> {
+    Raw {
+        source: &'a mut BinaryReader<R>,
+        data_offset: u64,
+    },
+    GZip(GzDecoder<StoredData<'a, R>>),
+    Zstd(zstd::stream::read::Decoder<'static, BufReader<StoredData<'a, R>>>),
+    ZstdMulti {
+        source: &'a mut BinaryReader<R>,
+        subchunks: Copied<slice::Iter<'a, WadSubchunk>>,
+        current_subchunk: Cursor<Vec<u8>>,
+    },
+}
+
+impl<R: Read + Seek> Wad<R> {
+    /// Opens a reader over the data of the entry with the given path hash
+    ///
+    /// The reader borrows the source of this WAD, so only one entry can be read at a time.
+    pub fn entry_reader(&mut self, xxhash: u64) -> Result<EntryReader<'_, R>, WadError> {
+        let entry = self
+            .entries
+            .get(&xxhash)
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+
+        EntryReader::new(&mut self.source, entry, self.subchunk_toc.as_deref())
+    }
+}
+
+impl<'a, R: Read + Seek> EntryReader<'a, R> {
+    pub(crate) fn new(
+        source: &'a mut BinaryReader<R>,
+        entry: &Entry,
+        subchunk_toc: Option<&'a [WadSubchunk]>,
+    ) -> Result<Self, WadError> {
+        let data_offset = entry.data_offset() as u64;
+        let compressed_size = entry.compressed_size() as u64;
+        source.seek(SeekFrom::Start(data_offset))?;
+
+        let (decoder, size) = match entry.data_format() {
+            EntryDataFormat::Raw => (
+                EntryDecoder::Raw {
+                    source,
+                    data_offset,
+                },
+                compressed_size,
+            ),
+            EntryDataFormat::GZip => (
+                EntryDecoder::GZip(GzDecoder::new(source.take(compressed_size))),
+                entry.uncompressed_size() as u64,
+            ),
+            EntryDataFormat::Zstd => (
+                EntryDecoder::Zstd(zstd::stream::read::Decoder::new(
+                    source.take(compressed_size),
+                )?),
+                entry.uncompressed_size() as u64,
+            ),
+            EntryDataFormat::ZstdMulti => {
+                let subchunk_toc =
+                    subchunk_toc.ok_or_else(|| WadError::MissingSubchunkToc(entry.xxhash()))?;
+                let subchunks = subchunk::entry_subchunks(entry, subchunk_toc)?;
+
+                (
+                    EntryDecoder::ZstdMulti {
+                        source,
+                        subchunks: subchunks.iter().copied(),
+                        current_subchunk: Cursor::new(Vec::new()),
+                    },
+                    entry.uncompressed_size() as u64,
+                )
+            }
+            format => return Err(WadError::UnsupportedEntryDataFormat(format)),
+        };
+
+        Ok(Self {
+            decoder,
+            xxhash: entry.xxhash(),
+            position: 0,
+            size,
+        })
+    }
+
+    pub fn xxhash(&self) -> u64 {
+        self.xxhash
+    }
+
+    /// The size of the data of the entry once it is decompressed
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn is_raw(&self) -> bool {
+        matches!(self.decoder, EntryDecoder::Raw { .. })
+    }
+
+    fn size_mismatch(&self, actual: u64) -> io::Error {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            WadError::DecompressedSizeMismatch(self.size as usize, actual as usize),
+        )
+    }
+}
+
+impl<'a, R: Read + Seek> Read for EntryReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let read = match &mut self.decoder {
+            EntryDecoder::Raw { source, .. } => {
+                let remaining = self.size.saturating_sub(self.position);
+                let length = buf.len().min(remaining as usize);
+                source.read(&mut buf[..length])?
+            }
+            EntryDecoder::GZip(decoder) => decoder.read(buf)?,
+            EntryDecoder::Zstd(decoder) => decoder.read(buf)?,
+            EntryDecoder::ZstdMulti {
+                source,
+                subchunks,
+                current_subchunk,
+            } => loop {
+                let read = current_subchunk.read(buf)?;
+                if read > 0 {
+                    break read;
+                }
+
+                let subchunk = match subchunks.next() {
+                    Some(subchunk) => subchunk,
+                    None => break 0,
+                };
+                let data = source.read_bytes(subchunk.compressed_size() as usize)?;
+                *current_subchunk = Cursor::new(
+                    subchunk
+                        .decompress(&data)
+                        .map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?,
+                );
+            },
+        };
+
+        // Raw entries are bounded by their size, decompressed ones have to match it exactly
+        let position = self.position + read as u64;
+        if position > self.size || (read == 0 && position < self.size && !self.is_raw()) {
+            return Err(self.size_mismatch(position));
+        }
+        self.position = position;
+
+        Ok(read)
+    }
+}
+
+impl<'a, R: Read + Seek> Seek for EntryReader<'a, R> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        if let EntryDecoder::Raw {
+            source,
+            data_offset,
+        } = &mut self.decoder
+        {
+            source.seek(SeekFrom::Start(*data_offset + target))?;
+            self.position = target;
+        } else if target >= self.position {
+            // Compressed data can only be skipped by decompressing it
+            let skipped = target - self.position;
+            io::copy(&mut self.by_ref().take(skipped), &mut io::sink())?;
+        } else {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "compressed entries can't be seeked backwards",
+            ));
+        }
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom};
+
+    use crate::streaming::binary_reader::BinaryReader;
+    use crate::wad::{EntryDataFormat, Wad, WadBuilder, WadError};
+
+    fn create_wad(data: &[u8]) -> Wad<Cursor<Vec<u8>>> {
+        let mut builder = WadBuilder::new();
+        builder.add_entry(1, data, EntryDataFormat::Raw).unwrap();
+        builder.add_entry(2, data, EntryDataFormat::GZip).unwrap();
+        builder.add_entry(3, data, EntryDataFormat::Zstd).unwrap();
+        builder
+            .add_entry(4, data, EntryDataFormat::FileRedirection)
+            .unwrap();
+
+        builder.mount()
+    }
+
+    #[test]
+    fn test_entry_reader() {
+        let data: Vec<u8> = (0..4096u32).flat_map(|i| i.to_le_bytes()).collect();
+        let mut wad = create_wad(&data);
+
+        for xxhash in 1..=3 {
+            let mut reader = wad.entry_reader(xxhash).unwrap();
+            assert_eq!(reader.size(), data.len() as u64);
+
+            let mut uncompressed_data = Vec::new();
+            reader.read_to_end(&mut uncompressed_data).unwrap();
+            assert_eq!(uncompressed_data, data);
+
+            // Skipping forward works for every format
+            let mut reader = wad.entry_reader(xxhash).unwrap();
+            reader.seek(SeekFrom::Start(4 * 1000)).unwrap();
+            let mut br = BinaryReader::new(reader);
+            assert_eq!(br.read_u32().unwrap(), 1000);
+            assert_eq!(br.read_u32().unwrap(), 1001);
+        }
+
+        let mut br = BinaryReader::new(wad.entry_reader(1).unwrap());
+        br.seek(SeekFrom::Start(4 * 2000)).unwrap();
+        assert_eq!(br.read_u32().unwrap(), 2000);
+        assert_eq!(br.position().unwrap(), 4 * 2001);
+
+        assert!(matches!(
+            wad.entry_reader(4),
+            Err(WadError::UnsupportedEntryDataFormat(
+                EntryDataFormat::FileRedirection
+            ))
+        ));
+        assert!(matches!(
+            wad.entry_reader(5),
+            Err(WadError::EntryNotFound(5))
+        ));
+    }
+
+    #[test]
+    fn test_entry_reader_seek() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut wad = create_wad(&data);
+
+        let mut reader = wad.entry_reader(1).unwrap();
+        let mut buffer = [0; 4];
+        reader.seek(SeekFrom::End(-4)).unwrap();
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, [252, 253, 254, 255]);
+        assert_eq!(reader.read(&mut buffer).unwrap(), 0);
+
+        reader.seek(SeekFrom::Start(16)).unwrap();
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, [16, 17, 18, 19]);
+        assert!(reader.seek(SeekFrom::Current(-64)).is_err());
+
+        let mut reader = wad.entry_reader(3).unwrap();
+        reader.seek(SeekFrom::Start(16)).unwrap();
+        assert_eq!(
+            reader.seek(SeekFrom::Start(0)).unwrap_err().kind(),
+            ErrorKind::Unsupported
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use getset::Getters;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use crate::league_file::identify;
+
+use super::{Entry, Wad, WadError, WadHashtable};
+
+/// Most filesystems limit file names to 255 bytes
+const MAX_FILE_NAME_LENGTH: usize = 255;
+
+#[derive(Debug, Default, Getters)]
+pub struct ExtractionSummary {
+    /// The path hashes of the extracted entries and the paths they were written to
+    #[getset(get = "pub")]
+    pub(crate) extracted: Vec<(u64, PathBuf)>,
+    /// The path hashes of the entries which couldn't be extracted
+    #[getset(get = "pub")]
+    pub(crate) failed: Vec<(u64, WadError)>,
+}
+
+/// Where an entry gets extracted to, relative to the destination directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ExtractionTarget {
+    Path(PathBuf),
+    /// The path of the entry is unknown, it is named after its path hash and the extension identified from its data
+    Unknown,
+}
+
+impl<R: Read + Seek> Wad<R> {
+    /// Extracts every entry to `destination`, using the paths from `hashtable` where they are known
+    ///
+    /// Entries with unknown, invalid or colliding paths are written as `<path hash>.<extension>`.
+    pub fn extract_all(
+        &mut self,
+        destination: &Path,
+        hashtable: &WadHashtable,
+    ) -> Result<ExtractionSummary, WadError> {
+        fs::create_dir_all(destination)?;
+
+        let mut summary = ExtractionSummary::default();
+        for (xxhash, target) in plan_extraction(self.entries.values(), hashtable) {
+            let result = self
+                .load_entry_data(xxhash)
+                .and_then(|data| write_entry(destination, xxhash, &target, &data));
+
+            match result {
+                Ok(path) => summary.extracted.push((xxhash, path)),
+                Err(error) => summary.failed.push((xxhash, error)),
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Decides where every entry gets extracted to, sorted by path hash
+pub(crate) fn plan_extraction<'a>(
+    entries: impl Iterator<Item = &'a Entry>,
+    hashtable: &WadHashtable,
+) -> Vec<(u64, ExtractionTarget)> {
+    let mut xxhashes: Vec<u64> = entries.map(Entry::xxhash).collect();
+    xxhashes.sort_unstable();
+
+    // Paths are compared case-insensitively so that extraction behaves the same on every filesystem
+    let mut files = HashSet::new();
+    let mut directories = HashSet::new();
+    xxhashes
+        .into_iter()
+        .map(|xxhash| {
+            let path = match hashtable.resolve(xxhash) {
+                Some(path) => path,
+                None => return (xxhash, ExtractionTarget::Unknown),
+            };
+
+            let components: Vec<&str> = path.split('/').collect();
+            if !components
+                .iter()
+                .all(|component| is_valid_component(component))
+            {
+                return (xxhash, ExtractionTarget::Unknown);
+            }
+
+            let mut target: PathBuf = components.iter().collect();
+            if components
+                .iter()
+                .any(|component| component.len() > MAX_FILE_NAME_LENGTH)
+            {
+                let file_name = hashed_file_name(xxhash, extension(path));
+                target = match target.parent() {
+                    Some(parent) if parent.iter().all(|c| c.len() <= MAX_FILE_NAME_LENGTH) => {
+                        parent.join(file_name)
+                    }
+                    _ => PathBuf::from(file_name),
+                };
+            }
+
+            let key = target.to_string_lossy().to_lowercase();
+            let ancestors: Vec<String> = target
+                .ancestors()
+                .skip(1)
+                .map(|ancestor| ancestor.to_string_lossy().to_lowercase())
+                .filter(|ancestor| !ancestor.is_empty())
+                .collect();
+            let collides = files.contains(&key)
+                || directories.contains(&key)
+                || ancestors.iter().any(|ancestor| files.contains(ancestor));
+            if collides {
+                target = PathBuf::from(hashed_file_name(xxhash, extension(path)));
+                files.insert(target.to_string_lossy().to_lowercase());
+            } else {
+                files.insert(key);
+                directories.extend(ancestors);
+            }
+
+            (xxhash, ExtractionTarget::Path(target))
+        })
+        .collect()
+}
+
+pub(crate) fn write_entry(
+    destination: &Path,
+    xxhash: u64,
+    target: &ExtractionTarget,
+    data: &[u8],
+) -> Result<PathBuf, WadError> {
+    let path = match target {
+        ExtractionTarget::Path(path) => destination.join(path),
+        ExtractionTarget::Unknown => {
+            destination.join(hashed_file_name(xxhash, identify(data).extension()))
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, data)?;
+
+    Ok(path)
+}
+
+fn is_valid_component(component: &str) -> bool {
+    !component.is_empty()
+        && component != "."
+        && component != ".."
+        && !component
+            .chars()
+            .any(|c| c.is_control() || "<>:\"\\|?*".contains(c))
+}
+
+fn extension(path: &str) -> Option<&str> {
+    let file_name = path.rsplit('/').next()?;
+    let (_, extension) = file_name.rsplit_once('.')?;
+
+    match extension.len() {
+        1..=16 => Some(extension),
+        _ => None,
+    }
+}
+
+fn hashed_file_name(xxhash: u64, extension: Option<&str>) -> String {
+    match extension {
+        Some(extension) => format!("{:016x}.{}", xxhash, extension),
+        None => format!("{:016x}", xxhash),
+    }
+}
+
+/// Returns the path hash a file is named after, the inverse of [`hashed_file_name`]
+pub(crate) fn parse_hashed_file_name(file_name: &str) -> Option<u64> {
+    let name = match file_name.split_once('.') {
+        Some((name, _)) => name,
+        None => file_name,
+    };
+
+    match name.len() == 16 && name.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => u64::from_str_radix(name, 16).ok(),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use xxhash_rust::xxh64::xxh64;
+
+    use crate::wad::{EntryDataFormat, WadBuilder, WadError, WadHashtable};
+
+    #[test]
+    fn test_extract_all() {
+        let long_name = format!("data/{}.bin", "a".repeat(300));
+        let paths = [
+            "data/characters/aatrox/aatrox.bin",
+            "data/File",
+            "data/file/nested.bin",
+            "DATA/FILE",
+            long_name.as_str(),
+        ];
+
+        let mut builder = WadBuilder::new();
+        let mut hashtable = WadHashtable::new();
+        for (i, path) in paths.iter().enumerate() {
+            // Hash the raw path so that differently cased paths don't share a hash
+            let xxhash = xxh64(path.as_bytes(), 0);
+            builder
+                .add_entry(xxhash, &[i as u8; 4], EntryDataFormat::Zstd)
+                .unwrap();
+            hashtable.insert(xxhash, path.to_string());
+        }
+        builder
+            .add_entry(1, b"PROP unknown", EntryDataFormat::Raw)
+            .unwrap();
+        builder
+            .add_entry(2, &[0, 0, 0, 0], EntryDataFormat::FileRedirection)
+            .unwrap();
+
+        let mut wad = builder.mount();
+
+        let destination = tempfile::tempdir().unwrap();
+        let summary = wad.extract_all(destination.path(), &hashtable).unwrap();
+
+        assert_eq!(summary.extracted().len(), 6);
+        assert_eq!(summary.failed().len(), 1);
+        assert!(matches!(
+            summary.failed()[0],
+            (
+                2,
+                WadError::UnsupportedEntryDataFormat(EntryDataFormat::FileRedirection)
+            )
+        ));
+
+        let root = destination.path();
+        assert_eq!(
+            fs::read(root.join("data/characters/aatrox/aatrox.bin")).unwrap(),
+            [0; 4]
+        );
+        assert_eq!(
+            fs::read(root.join("0000000000000001.bin")).unwrap(),
+            b"PROP unknown"
+        );
+        let long_name_file = root.join(format!("data/{:016x}.bin", xxh64(long_name.as_bytes(), 0)));
+        assert_eq!(fs::read(long_name_file).unwrap(), [4; 4]);
+
+        // Only one of "data/File", "data/file/nested.bin" and "DATA/FILE" can keep its path
+        let colliding: Vec<u64> = paths[1..4]
+            .iter()
+            .map(|path| xxh64(path.as_bytes(), 0))
+            .collect();
+        let fallback_count = summary
+            .extracted()
+            .iter()
+            .filter(|(xxhash, path)| colliding.contains(xxhash) && path.parent() == Some(root))
+            .count();
+        assert_eq!(fallback_count, 2);
+    }
+}
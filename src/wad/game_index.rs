@@ -0,0 +1,384 @@
+use getset::{CopyGetters, Getters};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::streaming::{binary_reader::BinaryReader, binary_writer::BinaryWriter};
+
+use super::{Wad, WadError};
+
+const CACHE_MAGIC: &str = "RWGI";
+const CACHE_VERSION: u8 = 1;
+
+const WAD_EXTENSIONS: &[&str] = &[".wad.client", ".wad.mobile", ".wad"];
+
+/// Knows which WADs of a game installation contain which entries, without keeping the WADs mounted
+#[derive(Debug, Default, Getters)]
+pub struct GameIndex {
+    /// The directory the WADs were found in, usually `Game/DATA`
+    #[getset(get = "pub")]
+    root: PathBuf,
+    /// The indexed WADs, sorted by path
+    #[getset(get = "pub")]
+    wads: Vec<IndexedWad>,
+    /// The WADs which couldn't be read or mounted
+    #[getset(get = "pub")]
+    failed: Vec<(PathBuf, WadError)>,
+
+    /// The indices of the WADs containing each entry
+    lookup: HashMap<u64, Vec<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+pub struct IndexedWad {
+    /// The path of the WAD relative to the root of the index
+    #[getset(get = "pub")]
+    path: PathBuf,
+    #[getset(get_copy = "pub")]
+    size: u64,
+    #[getset(get_copy = "pub")]
+    modified: SystemTime,
+    /// The path hashes of the entries of the WAD, sorted
+    #[getset(get = "pub")]
+    entries: Vec<u64>,
+}
+
+impl GameIndex {
+    /// Indexes every `.wad.client`, `.wad.mobile` and `.wad` file in `root` and its subdirectories
+    pub fn build(root: &Path) -> Result<Self, WadError> {
+        Self::build_from(root, Vec::new())
+    }
+
+    /// Same as [`GameIndex::build`], but WADs whose size and modification time match the cache at `cache_path`
+    /// are not mounted again
+    ///
+    /// The cache is rewritten afterwards. A missing or unreadable cache is treated as empty.
+    pub fn build_with_cache(root: &Path, cache_path: &Path) -> Result<Self, WadError> {
+        let cached_wads = File::open(cache_path)
+            .map_err(WadError::from)
+            .and_then(|file| read_cache(&mut BinaryReader::from_file(file)))
+            .unwrap_or_default();
+
+        let index = Self::build_from(root, cached_wads)?;
+        index.write_cache(&mut BinaryWriter::from_location(cache_path)?)?;
+
+        Ok(index)
+    }
+
+    fn build_from(root: &Path, cached_wads: Vec<IndexedWad>) -> Result<Self, WadError> {
+        let mut cached_wads: HashMap<String, IndexedWad> = cached_wads
+            .into_iter()
+            .map(|wad| (cache_key(&wad.path), wad))
+            .collect();
+
+        let mut wads = Vec::new();
+        let mut stale_wads = Vec::new();
+        let mut failed = Vec::new();
+        for path in find_wads(root)? {
+            // A WAD may vanish or be unreadable, which only excludes that WAD from the index
+            let (size, modified) = match fs::metadata(root.join(&path))
+                .and_then(|metadata| Ok((metadata.len(), metadata.modified()?)))
+            {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    failed.push((path, error.into()));
+                    continue;
+                }
+            };
+            match cached_wads.remove(&cache_key(&path)) {
+                Some(wad) if wad.size == size && wad.modified == modified => {
+                    wads.push(IndexedWad { path, ..wad })
+                }
+                _ => stale_wads.push((path, size, modified)),
+            }
+        }
+
+        let index_wad = |(path, size, modified): (PathBuf, u64, SystemTime)| {
+            let result = File::open(root.join(&path))
+                .map_err(WadError::from)
+                .and_then(Wad::mount)
+                .map(|wad| {
+                    let mut entries: Vec<u64> = wad.entries().keys().copied().collect();
+                    entries.sort_unstable();
+                    entries
+                });
+
+            (path, size, modified, result)
+        };
+        #[cfg(feature = "rayon")]
+        let results: Vec<_> = stale_wads.into_par_iter().map(index_wad).collect();
+        #[cfg(not(feature = "rayon"))]
+        let results: Vec<_> = stale_wads.into_iter().map(index_wad).collect();
+
+        for result in results {
+            match result {
+                (path, size, modified, Ok(entries)) => wads.push(IndexedWad {
+                    path,
+                    size,
+                    modified,
+                    entries,
+                }),
+                (path, _, _, Err(error)) => failed.push((path, error)),
+            }
+        }
+        wads.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+        let mut lookup: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, wad) in wads.iter().enumerate() {
+            for &xxhash in &wad.entries {
+                lookup.entry(xxhash).or_default().push(i);
+            }
+        }
+
+        Ok(GameIndex {
+            root: root.to_path_buf(),
+            wads,
+            failed,
+            lookup,
+        })
+    }
+
+    /// Returns the WADs containing the entry with the given path hash
+    pub fn find(&self, xxhash: u64) -> impl Iterator<Item = &IndexedWad> {
+        self.lookup
+            .get(&xxhash)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(move |&i| &self.wads[i])
+    }
+
+    /// Mounts the WAD at `path`, relative to the root of the index
+    pub fn mount(&self, path: &Path) -> Result<Wad, WadError> {
+        Wad::mount_from_path(&self.root.join(path))
+    }
+
+    /// Loads the decompressed data of the entry with the given path hash from the first WAD containing it
+    pub fn load_entry_data(&self, xxhash: u64) -> Result<Vec<u8>, WadError> {
+        let wad = self
+            .find(xxhash)
+            .next()
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+
+        self.mount(&wad.path)?.load_entry_data(xxhash)
+    }
+
+    /// Writes the indexed WADs so that they can be reused by [`GameIndex::build_with_cache`]
+    pub fn write_cache<W: Write + Seek>(&self, bw: &mut BinaryWriter<W>) -> Result<(), WadError> {
+        bw.write_string(CACHE_MAGIC)?;
+        bw.write_u8(CACHE_VERSION)?;
+        bw.write_u32(self.wads.len() as u32)?;
+        for wad in &self.wads {
+            let path = cache_key(&wad.path);
+            let modified = wad.modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+            bw.write_u16(
+                u16::try_from(path.len()).map_err(|_| WadError::PathTooLong(path.clone()))?,
+            )?;
+            bw.write_string(&path)?;
+            bw.write_u64(wad.size)?;
+            bw.write_u64(modified.as_secs())?;
+            bw.write_u32(modified.subsec_nanos())?;
+            bw.write_u32(wad.entries.len() as u32)?;
+            for &xxhash in &wad.entries {
+                bw.write_u64(xxhash)?;
+            }
+        }
+
+        Ok(bw.flush()?)
+    }
+}
+
+fn read_cache<R: Read + Seek>(br: &mut BinaryReader<R>) -> Result<Vec<IndexedWad>, WadError> {
+    let magic = br.read_string(4)?;
+    if magic != CACHE_MAGIC {
+        return Err(WadError::InvalidSignature(magic));
+    }
+    let version = br.read_u8()?;
+    if version != CACHE_VERSION {
+        return Err(WadError::UnsupportedGameIndexCacheVersion(version));
+    }
+
+    let wad_count = br.read_u32()?;
+    let mut wads = Vec::new();
+    for _ in 0..wad_count {
+        let path_length = br.read_u16()? as usize;
+        let path = PathBuf::from(br.read_string(path_length)?);
+        let size = br.read_u64()?;
+        let (secs, nanos) = (br.read_u64()?, br.read_u32()?);
+        let modified = Some(nanos)
+            .filter(|&nanos| nanos < 1_000_000_000)
+            .and_then(|nanos| UNIX_EPOCH.checked_add(Duration::new(secs, nanos)))
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid modification time")
+            })?;
+
+        let entry_count = br.read_u32()?;
+        let mut entries = Vec::new();
+        for _ in 0..entry_count {
+            entries.push(br.read_u64()?);
+        }
+
+        wads.push(IndexedWad {
+            path,
+            size,
+            modified,
+            entries,
+        });
+    }
+
+    Ok(wads)
+}
+
+/// Paths are stored with forward slashes so that the cache doesn't depend on the platform
+fn cache_key(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Finds every WAD in `root` and its subdirectories, returning their paths relative to `root`
+fn find_wads(root: &Path) -> Result<Vec<PathBuf>, WadError> {
+    let mut wads = Vec::new();
+    let mut directories = vec![root.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                directories.push(path);
+                continue;
+            }
+
+            let file_name = entry.file_name().to_string_lossy().to_lowercase();
+            if WAD_EXTENSIONS
+                .iter()
+                .any(|extension| file_name.ends_with(extension))
+            {
+                if let Ok(path) = path.strip_prefix(root) {
+                    wads.push(path.to_path_buf());
+                }
+            }
+        }
+    }
+
+    Ok(wads)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Cursor,
+        path::{Path, PathBuf},
+    };
+
+    use super::{read_cache, CACHE_VERSION};
+    use crate::streaming::{binary_reader::BinaryReader, binary_writer::BinaryWriter};
+    use crate::wad::{EntryDataFormat, GameIndex, WadBuilder, WadError};
+
+    fn write_wad(path: &Path, entries: &[(u64, &[u8])]) {
+        let mut builder = WadBuilder::new();
+        for (xxhash, data) in entries {
+            builder
+                .add_entry(*xxhash, data, EntryDataFormat::Zstd)
+                .unwrap();
+        }
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, builder.to_buffer()).unwrap();
+    }
+
+    fn wad_paths(index: &GameIndex, xxhash: u64) -> Vec<PathBuf> {
+        index.find(xxhash).map(|wad| wad.path().clone()).collect()
+    }
+
+    #[test]
+    fn test_game_index() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path().join("DATA");
+        let aatrox = Path::new("FINAL/Champions/Aatrox.wad.client");
+        let common = Path::new("FINAL/Common.wad.mobile");
+        write_wad(&root.join(aatrox), &[(1, b"aatrox"), (2, b"shared")]);
+        write_wad(&root.join(common), &[(2, b"shared"), (3, b"common")]);
+        fs::write(root.join("FINAL/Broken.wad"), b"not a wad").unwrap();
+        fs::write(root.join("FINAL/readme.txt"), b"not a wad either").unwrap();
+
+        let index = GameIndex::build(&root).unwrap();
+        assert_eq!(index.wads().len(), 2);
+        assert_eq!(index.failed().len(), 1);
+        assert_eq!(index.failed()[0].0, Path::new("FINAL/Broken.wad"));
+
+        assert_eq!(wad_paths(&index, 1), vec![aatrox]);
+        assert_eq!(wad_paths(&index, 2), vec![aatrox, common]);
+        assert!(wad_paths(&index, 4).is_empty());
+        assert_eq!(index.load_entry_data(3).unwrap(), b"common");
+
+        // A WAD which can't be read doesn't fail the whole index
+        #[cfg(unix)]
+        {
+            let missing = Path::new("FINAL/Missing.wad.client");
+            std::os::unix::fs::symlink(root.join("FINAL/Gone.wad"), root.join(missing)).unwrap();
+            let index = GameIndex::build(&root).unwrap();
+            assert_eq!(index.wads().len(), 2);
+            assert!(index.failed().iter().any(|(path, _)| path == missing));
+        }
+    }
+
+    #[test]
+    fn test_game_index_cache() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path().join("DATA");
+        let cache_path = directory.path().join("index.cache");
+        let wad_path = root.join("FINAL/Test.wad.client");
+        write_wad(&wad_path, &[(1, b"first")]);
+
+        let index = GameIndex::build_with_cache(&root, &cache_path).unwrap();
+        assert_eq!(wad_paths(&index, 1).len(), 1);
+
+        // An outdated cache entry is detected by the size of the WAD
+        write_wad(&wad_path, &[(1, b"first"), (2, b"second")]);
+        let index = GameIndex::build_with_cache(&root, &cache_path).unwrap();
+        assert_eq!(wad_paths(&index, 2).len(), 1);
+
+        // An up to date cache entry is trusted without mounting the WAD
+        fs::write(&cache_path, {
+            let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+            index.write_cache(&mut bw).unwrap();
+            let mut cache = bw.into_inner().unwrap().into_inner();
+            let length = cache.len();
+            cache[length - 8..].copy_from_slice(&3u64.to_le_bytes());
+            cache
+        })
+        .unwrap();
+        let index = GameIndex::build_with_cache(&root, &cache_path).unwrap();
+        assert_eq!(wad_paths(&index, 3).len(), 1);
+        assert!(wad_paths(&index, 2).is_empty());
+
+        // A corrupted modification time makes the cache unreadable, so it is rebuilt
+        fs::write(&cache_path, {
+            let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+            index.write_cache(&mut bw).unwrap();
+            let mut cache = bw.into_inner().unwrap().into_inner();
+            let modified_offset = cache.len() - 8 - 4 - 4 - 8;
+            cache[modified_offset..modified_offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+            cache
+        })
+        .unwrap();
+        let index = GameIndex::build_with_cache(&root, &cache_path).unwrap();
+        assert_eq!(wad_paths(&index, 2).len(), 1);
+
+        let mut cache = fs::read(&cache_path).unwrap();
+        cache[4] = CACHE_VERSION + 1;
+        assert!(matches!(
+            read_cache(&mut BinaryReader::from_buffer(Cursor::new(cache))),
+            Err(WadError::UnsupportedGameIndexCacheVersion(version)) if version == CACHE_VERSION + 1
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+use xxhash_rust::xxh64::xxh64;
+
+/// Normalizes `path` the same way the game does before hashing it: lowercase with forward slashes
+pub fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/").to_lowercase()
+}
+
+/// Computes the path hash of an entry, as stored in [`Entry::xxhash`](super::Entry::xxhash)
+pub fn hash_path(path: &str) -> u64 {
+    xxh64(normalize_path(path).as_bytes(), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use xxhash_rust::xxh64::xxh64;
+
+    use super::{hash_path, normalize_path};
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path("DATA\\Characters\\Aatrox/Skins/Skin0.bin"),
+            "data/characters/aatrox/skins/skin0.bin"
+        );
+    }
+
+    #[test]
+    fn test_hash_path() {
+        let xxhash = xxh64(b"assets/characters/aatrox/aatrox.skn", 0);
+
+        assert_eq!(hash_path("assets/characters/aatrox/aatrox.skn"), xxhash);
+        assert_eq!(hash_path("ASSETS/Characters/Aatrox/Aatrox.skn"), xxhash);
+        assert_eq!(hash_path("assets\\characters\\aatrox\\aatrox.skn"), xxhash);
+    }
+}
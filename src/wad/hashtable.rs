@@ -0,0 +1,254 @@
+use getset::Getters;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, Write},
+    path::Path,
+};
+
+use crate::streaming::{binary_reader::BinaryReader, binary_writer::BinaryWriter};
+
+use super::{Entry, Wad, WadError};
+
+const BINARY_MAGIC: &str = "RWHT";
+const BINARY_VERSION: u8 = 1;
+
+/// Maps path hashes back to the paths they were computed from
+#[derive(Debug, Default, Clone, Getters)]
+pub struct WadHashtable {
+    #[getset(get = "pub")]
+    items: HashMap<u64, String>,
+}
+
+impl WadHashtable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a hashtable in the text format used by `hashes.game.txt` and `hashes.lcu.txt`
+    pub fn from_text_path(path: &Path) -> Result<Self, WadError> {
+        Self::read_text(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a hashtable in the text format, where every line is a hex path hash followed by a space and the path
+    pub fn read_text<R: BufRead>(reader: R) -> Result<Self, WadError> {
+        let mut hashtable = Self::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (xxhash, path) = line
+                .split_once(' ')
+                .and_then(|(xxhash, path)| Some((u64::from_str_radix(xxhash, 16).ok()?, path)))
+                .ok_or(WadError::InvalidHashtableLine(i + 1))?;
+            hashtable.items.insert(xxhash, path.to_string());
+        }
+
+        Ok(hashtable)
+    }
+
+    /// Writes the hashtable in the text format, sorted by path
+    pub fn write_text<W: Write>(&self, mut writer: W) -> Result<(), WadError> {
+        let mut items: Vec<(&u64, &String)> = self.items.iter().collect();
+        items.sort_unstable_by_key(|(_, path)| *path);
+
+        for (xxhash, path) in items {
+            writeln!(writer, "{:016x} {}", xxhash, path)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn from_binary_path(path: &Path) -> Result<Self, WadError> {
+        Self::read_binary(&mut BinaryReader::from_file(File::open(path)?))
+    }
+
+    pub fn read_binary<R: Read + Seek>(br: &mut BinaryReader<R>) -> Result<Self, WadError> {
+        let magic = br.read_string(4)?;
+        if magic != BINARY_MAGIC {
+            return Err(WadError::InvalidSignature(magic));
+        }
+        let version = br.read_u8()?;
+        if version != BINARY_VERSION {
+            return Err(WadError::UnsupportedHashtableVersion(version));
+        }
+
+        // The count isn't trusted for a preallocation, the file may be truncated or corrupted
+        let count = br.read_u32()?;
+        let mut items = HashMap::new();
+        for _ in 0..count {
+            let xxhash = br.read_u64()?;
+            let length = br.read_u16()? as usize;
+            items.insert(xxhash, br.read_string(length)?);
+        }
+
+        Ok(WadHashtable { items })
+    }
+
+    pub fn write_binary_to_path(&self, path: &Path) -> Result<(), WadError> {
+        self.write_binary(&mut BinaryWriter::from_file(File::create(path)?))
+    }
+
+    /// Writes the hashtable in a compact binary form which is faster to load than the text format
+    pub fn write_binary<W: Write + Seek>(&self, bw: &mut BinaryWriter<W>) -> Result<(), WadError> {
+        let mut items: Vec<(&u64, &String)> = self.items.iter().collect();
+        items.sort_unstable_by_key(|(&xxhash, _)| xxhash);
+
+        bw.write_string(BINARY_MAGIC)?;
+        bw.write_u8(BINARY_VERSION)?;
+        bw.write_u32(items.len() as u32)?;
+        for (&xxhash, path) in items {
+            bw.write_u64(xxhash)?;
+            bw.write_u16(
+                u16::try_from(path.len()).map_err(|_| WadError::PathTooLong(path.clone()))?,
+            )?;
+            bw.write_string(path)?;
+        }
+
+        Ok(bw.flush()?)
+    }
+
+    pub fn insert(&mut self, xxhash: u64, path: String) -> Option<String> {
+        self.items.insert(xxhash, path)
+    }
+
+    /// Merges `other` into this hashtable, paths from `other` take precedence
+    pub fn merge(&mut self, other: WadHashtable) {
+        self.items.extend(other.items);
+    }
+
+    pub fn resolve(&self, xxhash: u64) -> Option<&str> {
+        self.items.get(&xxhash).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl<R: Read + Seek> Wad<R> {
+    /// Iterates the entries of this WAD together with their path, if it is known by `hashtable`
+    pub fn resolved_entries<'a>(
+        &'a self,
+        hashtable: &'a WadHashtable,
+    ) -> impl Iterator<Item = (Option<&'a str>, &'a Entry)> {
+        self.entries
+            .values()
+            .map(move |entry| (hashtable.resolve(entry.xxhash()), entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::streaming::{binary_reader::BinaryReader, binary_writer::BinaryWriter};
+    use crate::wad::{hash_path, EntryDataFormat, WadBuilder, WadError, WadHashtable};
+
+    const TEXT: &str = "\
+0000000000000001 data/first.bin
+00000000000000ff assets/path with spaces.dds
+
+";
+
+    #[test]
+    fn test_read_text() {
+        let hashtable = WadHashtable::read_text(TEXT.as_bytes()).unwrap();
+
+        assert_eq!(hashtable.len(), 2);
+        assert_eq!(hashtable.resolve(1), Some("data/first.bin"));
+        assert_eq!(hashtable.resolve(0xFF), Some("assets/path with spaces.dds"));
+        assert_eq!(hashtable.resolve(2), None);
+        assert!(matches!(
+            WadHashtable::read_text("0000000000000001 a\nnot a hash\n".as_bytes()),
+            Err(WadError::InvalidHashtableLine(2))
+        ));
+    }
+
+    #[test]
+    fn test_write_text() {
+        let hashtable = WadHashtable::read_text(TEXT.as_bytes()).unwrap();
+        let mut text = Vec::new();
+        hashtable.write_text(&mut text).unwrap();
+
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "00000000000000ff assets/path with spaces.dds\n0000000000000001 data/first.bin\n"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut hashtable = WadHashtable::read_text(TEXT.as_bytes()).unwrap();
+        let mut other = WadHashtable::new();
+        other.insert(1, "data/renamed.bin".to_string());
+        other.insert(2, "data/second.bin".to_string());
+        hashtable.merge(other);
+
+        assert_eq!(hashtable.len(), 3);
+        assert_eq!(hashtable.resolve(1), Some("data/renamed.bin"));
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let hashtable = WadHashtable::read_text(TEXT.as_bytes()).unwrap();
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        hashtable.write_binary(&mut bw).unwrap();
+
+        let mut buffer = bw.into_inner().unwrap().into_inner();
+        let read_hashtable =
+            WadHashtable::read_binary(&mut BinaryReader::from_buffer(Cursor::new(buffer.clone())))
+                .unwrap();
+        assert_eq!(read_hashtable.items(), hashtable.items());
+
+        buffer[4] = 2;
+        assert!(matches!(
+            WadHashtable::read_binary(&mut BinaryReader::from_buffer(Cursor::new(buffer))),
+            Err(WadError::UnsupportedHashtableVersion(2))
+        ));
+
+        let mut hashtable = WadHashtable::new();
+        hashtable.insert(1, "a".repeat(u16::MAX as usize + 1));
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        assert!(matches!(
+            hashtable.write_binary(&mut bw),
+            Err(WadError::PathTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolved_entries() {
+        let mut builder = WadBuilder::new();
+        builder
+            .add_path_entry("data/known.bin", b"known", EntryDataFormat::Raw)
+            .unwrap();
+        builder
+            .add_entry(1, b"unknown", EntryDataFormat::Raw)
+            .unwrap();
+        let wad = builder.mount();
+
+        let mut hashtable = WadHashtable::new();
+        hashtable.insert(hash_path("data/known.bin"), "data/known.bin".to_string());
+
+        let mut resolved: Vec<(Option<&str>, u64)> = wad
+            .resolved_entries(&hashtable)
+            .map(|(path, entry)| (path, entry.xxhash()))
+            .collect();
+        resolved.sort_unstable();
+        assert_eq!(
+            resolved,
+            vec![
+                (None, 1),
+                (Some("data/known.bin"), hash_path("data/known.bin"))
+            ]
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use getset::{CopyGetters, Getters};
+use std::io::{Read, Seek, Write};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::streaming::{binary_reader::BinaryReader, binary_writer::BinaryWriter};
+
+use super::{EntryDataChecksumKind, WadError};
+
+const V2_ECDSA_SIGNATURE_SIZE: usize = 83;
+const V3_ECDSA_SIGNATURE_SIZE: usize = 256;
+const V3_HEADER_SIZE: u16 = 272;
+const V3_TOC_ENTRY_SIZE: u16 = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WadHeader {
+    #[getset(get_copy = "pub")]
+    major: u8,
+    #[getset(get_copy = "pub")]
+    minor: u8,
+
+    #[getset(get = "pub")]
+    ecdsa_signature: Vec<u8>,
+    #[getset(get_copy = "pub")]
+    toc_checksum: u64,
+
+    #[getset(get_copy = "pub")]
+    toc_offset: u16,
+    #[getset(get_copy = "pub")]
+    toc_entry_size: u16,
+
+    /// The length prefixed signature block of a v2 header as it was read, including the bytes after the signature
+    #[cfg_attr(feature = "serde", serde(skip))]
+    v2_signature_block: Vec<u8>,
+}
+
+impl Default for WadHeader {
+    /// An unsigned v3.1 header
+    fn default() -> Self {
+        Self::unsigned(3, 1)
+    }
+}
+
+impl WadHeader {
+    /// Creates an unsigned header of the given version, with the TOC placed right after it
+    pub fn new(major: u8, minor: u8) -> Result<Self, WadError> {
+        match (major, minor) {
+            (1, 0..=1) | (2, 0..=1) | (3, 0..=4) => Ok(Self::unsigned(major, minor)),
+            _ => Err(WadError::UnsupportedVersion(major, minor)),
+        }
+    }
+
+    fn unsigned(major: u8, minor: u8) -> Self {
+        let mut header = WadHeader {
+            major,
+            minor,
+            ecdsa_signature: Vec::new(),
+            toc_checksum: 0,
+            toc_offset: 0,
+            toc_entry_size: 0,
+            v2_signature_block: Vec::new(),
+        };
+        header.toc_offset = header.size() as u16;
+        header.toc_entry_size = header.required_toc_entry_size();
+
+        header
+    }
+
+    /// Reads the header and returns it together with the entry count of the TOC
+    pub(crate) fn read<R: Read + Seek>(br: &mut BinaryReader<R>) -> Result<(Self, u32), WadError> {
+        let magic = br.read_string(2)?;
+        if magic != "RW" {
+            return Err(WadError::InvalidSignature(magic));
+        }
+
+        let major = br.read_u8()?;
+        let minor = br.read_u8()?;
+        match (major, minor) {
+            (1, 0) | (1, 1) => {
+                let toc_offset = br.read_u16()?;
+                let toc_entry_size = br.read_u16()?;
+                let entry_count = br.read_u32()?;
+
+                Ok((
+                    WadHeader {
+                        major,
+                        minor,
+                        ecdsa_signature: Vec::new(),
+                        toc_checksum: 0,
+                        toc_offset,
+                        toc_entry_size,
+                        v2_signature_block: Vec::new(),
+                    },
+                    entry_count,
+                ))
+            }
+            (2, 0) | (2, 1) => {
+                // The signature is stored in a fixed size block prefixed by its actual length
+                let v2_signature_block = br.read_bytes(1 + V2_ECDSA_SIGNATURE_SIZE)?;
+                let ecdsa_signature_length =
+                    (v2_signature_block[0] as usize).min(V2_ECDSA_SIGNATURE_SIZE);
+                let ecdsa_signature = v2_signature_block[1..1 + ecdsa_signature_length].to_vec();
+
+                let toc_checksum = br.read_u64()?;
+                let toc_offset = br.read_u16()?;
+                let toc_entry_size = br.read_u16()?;
+                let entry_count = br.read_u32()?;
+
+                Ok((
+                    WadHeader {
+                        major,
+                        minor,
+                        ecdsa_signature,
+                        toc_checksum,
+                        toc_offset,
+                        toc_entry_size,
+                        v2_signature_block,
+                    },
+                    entry_count,
+                ))
+            }
+            (3, 0..=4) => {
+                let ecdsa_signature = br.read_bytes(V3_ECDSA_SIGNATURE_SIZE)?;
+                let toc_checksum = br.read_u64()?;
+                let entry_count = br.read_u32()?;
+
+                Ok((
+                    WadHeader {
+                        major,
+                        minor,
+                        ecdsa_signature,
+                        toc_checksum,
+                        toc_offset: V3_HEADER_SIZE,
+                        toc_entry_size: V3_TOC_ENTRY_SIZE,
+                        v2_signature_block: Vec::new(),
+                    },
+                    entry_count,
+                ))
+            }
+            _ => Err(WadError::UnsupportedVersion(major, minor)),
+        }
+    }
+
+    /// Writes the header in the layout of its version, followed by `entry_count`
+    pub(crate) fn write<W: Write + Seek>(
+        &self,
+        bw: &mut BinaryWriter<W>,
+        entry_count: u32,
+    ) -> Result<(), WadError> {
+        bw.write_string("RW")?;
+        bw.write_u8(self.major)?;
+        bw.write_u8(self.minor)?;
+        match self.major {
+            1 => {
+                bw.write_u16(self.toc_offset)?;
+                bw.write_u16(self.toc_entry_size)?;
+            }
+            2 => {
+                if self.v2_signature_block.len() == 1 + V2_ECDSA_SIGNATURE_SIZE {
+                    bw.write_slice(&self.v2_signature_block)?;
+                } else {
+                    let ecdsa_signature = padded(&self.ecdsa_signature, V2_ECDSA_SIGNATURE_SIZE);
+                    bw.write_u8(self.ecdsa_signature.len().min(V2_ECDSA_SIGNATURE_SIZE) as u8)?;
+                    bw.write_slice(&ecdsa_signature)?;
+                }
+                bw.write_u64(self.toc_checksum)?;
+                bw.write_u16(self.toc_offset)?;
+                bw.write_u16(self.toc_entry_size)?;
+            }
+            3 => {
+                bw.write_slice(&padded(&self.ecdsa_signature, V3_ECDSA_SIGNATURE_SIZE))?;
+                bw.write_u64(self.toc_checksum)?;
+            }
+            _ => return Err(WadError::UnsupportedVersion(self.major, self.minor)),
+        }
+        bw.write_u32(entry_count)?;
+
+        Ok(())
+    }
+
+    /// The size of the header, including the entry count
+    pub(crate) fn size(&self) -> u64 {
+        match self.major {
+            1 => 12,
+            2 => 104,
+            _ => V3_HEADER_SIZE as u64,
+        }
+    }
+
+    /// The offset of the entry count, which is the last field of the header
+    pub(crate) fn entry_count_offset(&self) -> u64 {
+        self.size() - 4
+    }
+
+    /// The number of bytes a TOC entry of this version needs, entries may be padded beyond this by the TOC entry size
+    pub(crate) fn required_toc_entry_size(&self) -> u16 {
+        match self.major {
+            1 => 24,
+            _ => V3_TOC_ENTRY_SIZE,
+        }
+    }
+
+    /// The kind of checksum stored in the TOC entries of this version
+    pub fn checksum_kind(&self) -> EntryDataChecksumKind {
+        match (self.major, self.minor) {
+            (1, _) => EntryDataChecksumKind::None,
+            (2, _) | (3, 0) => EntryDataChecksumKind::Sha256,
+            _ => EntryDataChecksumKind::XxHash3,
+        }
+    }
+}
+
+/// Truncates or zero pads `data` to `size` bytes
+fn padded(data: &[u8], size: usize) -> Vec<u8> {
+    let mut data = data[..data.len().min(size)].to_vec();
+    data.resize(size, 0);
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::streaming::binary_reader::BinaryReader;
+    use crate::wad::{EntryDataChecksum, EntryDataChecksumKind, Wad, WadError, WadHeader};
+
+    fn push_toc_entry(buffer: &mut Vec<u8>, xxhash: u64, data_offset: u32, data_size: i32) {
+        buffer.extend_from_slice(&xxhash.to_le_bytes());
+        buffer.extend_from_slice(&data_offset.to_le_bytes());
+        buffer.extend_from_slice(&data_size.to_le_bytes());
+        buffer.extend_from_slice(&data_size.to_le_bytes());
+        buffer.extend_from_slice(&[0, 0, 0, 0]);
+    }
+
+    fn read(buffer: Vec<u8>) -> Result<Wad<Cursor<Vec<u8>>>, WadError> {
+        Wad::read(BinaryReader::from_buffer(Cursor::new(buffer)))
+    }
+
+    #[test]
+    fn test_read_v1() {
+        let mut buffer = b"RW".to_vec();
+        buffer.extend_from_slice(&[1, 1]);
+        buffer.extend_from_slice(&12u16.to_le_bytes());
+        buffer.extend_from_slice(&24u16.to_le_bytes());
+        buffer.extend_from_slice(&1u32.to_le_bytes());
+        push_toc_entry(&mut buffer, 1, 36, 4);
+        buffer.extend_from_slice(b"data");
+
+        let mut wad = read(buffer).unwrap();
+        assert_eq!(wad.header().major(), 1);
+        assert_eq!(wad.header().toc_entry_size(), 24);
+        assert_eq!(wad.header().checksum_kind(), EntryDataChecksumKind::None);
+        assert!(matches!(
+            wad.entries()[&1].data_checksum(),
+            EntryDataChecksum::None
+        ));
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_read_v2() {
+        let mut buffer = b"RW".to_vec();
+        buffer.extend_from_slice(&[2, 0]);
+        buffer.push(4);
+        buffer.extend_from_slice(&[0xAB; 4]);
+        buffer.extend_from_slice(&[0; 79]);
+        buffer.extend_from_slice(&0x1234u64.to_le_bytes());
+        // The TOC is placed after some padding to make sure the offset is respected
+        buffer.extend_from_slice(&108u16.to_le_bytes());
+        buffer.extend_from_slice(&32u16.to_le_bytes());
+        buffer.extend_from_slice(&1u32.to_le_bytes());
+        buffer.extend_from_slice(&[0; 4]);
+        push_toc_entry(&mut buffer, 1, 140, 4);
+        buffer.extend_from_slice(&[0xCD; 8]);
+        buffer.extend_from_slice(b"data");
+
+        let mut wad = read(buffer).unwrap();
+        assert_eq!(wad.header().ecdsa_signature(), &[0xAB; 4]);
+        assert_eq!(wad.header().toc_checksum(), 0x1234);
+        assert_eq!(wad.header().toc_offset(), 108);
+        assert_eq!(wad.header().checksum_kind(), EntryDataChecksumKind::Sha256);
+        match wad.entries()[&1].data_checksum() {
+            EntryDataChecksum::Sha256(checksum) => assert_eq!(checksum, &[0xCD; 8]),
+            _ => panic!("expected a SHA-256 checksum"),
+        }
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_read_v3() {
+        for minor in 0..=4 {
+            let mut buffer = b"RW".to_vec();
+            buffer.extend_from_slice(&[3, minor]);
+            buffer.extend_from_slice(&[0; 256]);
+            buffer.extend_from_slice(&0u64.to_le_bytes());
+            buffer.extend_from_slice(&0u32.to_le_bytes());
+
+            let wad = read(buffer).unwrap();
+            assert_eq!(wad.header().minor(), minor);
+            assert_eq!(wad.header().toc_offset(), 272);
+            assert_eq!(
+                wad.header().checksum_kind(),
+                match minor {
+                    0 => EntryDataChecksumKind::Sha256,
+                    _ => EntryDataChecksumKind::XxHash3,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        for &(major, minor, toc_offset, toc_entry_size) in
+            &[(1, 0, 12, 24), (2, 1, 104, 32), (3, 4, 272, 32)]
+        {
+            let header = WadHeader::new(major, minor).unwrap();
+            assert_eq!(header.toc_offset(), toc_offset);
+            assert_eq!(header.toc_entry_size(), toc_entry_size);
+            assert!(header.ecdsa_signature().is_empty());
+        }
+        assert!(matches!(
+            WadHeader::new(3, 5),
+            Err(WadError::UnsupportedVersion(3, 5))
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        for &(major, minor) in &[(0, 1), (3, 5), (4, 0)] {
+            let mut buffer = b"RW".to_vec();
+            buffer.extend_from_slice(&[major, minor]);
+            buffer.extend_from_slice(&[0; 272]);
+
+            assert!(matches!(
+                read(buffer),
+                Err(WadError::UnsupportedVersion(error_major, error_minor))
+                    if (error_major, error_minor) == (major, minor)
+            ));
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use getset::{CopyGetters, Getters};
+use std::io::{Read, Seek, Write};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{Entry, EntryDataChecksum, EntryDataFormat, Wad, WadError, WadHashtable};
+
+const CSV_HEADER: &str =
+    "xxhash,path,data_format,compressed_size,uncompressed_size,checksum,data_offset";
+
+/// The metadata of an entry as listed in a [`WadManifest`]
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ManifestEntry {
+    /// Written as 16 hex digits, since JSON consumers often can't represent every `u64`
+    #[getset(get_copy = "pub")]
+    #[cfg_attr(feature = "serde", serde(with = "hex_hash"))]
+    xxhash: u64,
+    /// The path of the entry, if it is known by the hashtable passed to [`Wad::manifest`]
+    #[getset(get = "pub")]
+    path: Option<String>,
+    #[getset(get_copy = "pub")]
+    data_format: EntryDataFormat,
+    #[getset(get_copy = "pub")]
+    compressed_size: i32,
+    #[getset(get_copy = "pub")]
+    uncompressed_size: i32,
+    /// The checksum of the stored data in hex, `None` if the version of the WAD doesn't store checksums
+    #[getset(get = "pub")]
+    checksum: Option<String>,
+    #[getset(get_copy = "pub")]
+    data_offset: u32,
+}
+
+/// The table of contents of a WAD, in TOC order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WadManifest {
+    #[getset(get = "pub")]
+    entries: Vec<ManifestEntry>,
+}
+
+impl<R: Read + Seek> Wad<R> {
+    /// Lists the metadata of every entry, resolving their paths through `hashtable`
+    pub fn manifest(&self, hashtable: Option<&WadHashtable>) -> WadManifest {
+        let entries = self
+            .toc_order
+            .iter()
+            .map(|xxhash| {
+                let path = hashtable.and_then(|hashtable| hashtable.resolve(*xxhash));
+
+                ManifestEntry::new(&self.entries[xxhash], path)
+            })
+            .collect();
+
+        WadManifest { entries }
+    }
+}
+
+impl ManifestEntry {
+    fn new(entry: &Entry, path: Option<&str>) -> Self {
+        let checksum = match &entry.data_checksum {
+            EntryDataChecksum::Sha256(checksum) | EntryDataChecksum::XxHash3(checksum) => Some(
+                checksum
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect(),
+            ),
+            EntryDataChecksum::None => None,
+        };
+
+        Self {
+            xxhash: entry.xxhash,
+            path: path.map(str::to_string),
+            data_format: entry.data_format,
+            compressed_size: entry.compressed_size,
+            uncompressed_size: entry.uncompressed_size,
+            checksum,
+            data_offset: entry.data_offset,
+        }
+    }
+}
+
+impl WadManifest {
+    /// Writes the manifest as CSV with a header row, unknown paths and checksums are left empty
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), WadError> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for entry in &self.entries {
+            writeln!(
+                writer,
+                "{:016x},{},{:?},{},{},{},{}",
+                entry.xxhash,
+                csv_field(entry.path.as_deref().unwrap_or_default()),
+                entry.data_format,
+                entry.compressed_size,
+                entry.uncompressed_size,
+                entry.checksum.as_deref().unwrap_or_default(),
+                entry.data_offset
+            )?;
+        }
+
+        Ok(writer.flush()?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), WadError> {
+        serde_json::to_writer_pretty(writer, self).map_err(std::io::Error::from)?;
+
+        Ok(())
+    }
+}
+
+/// Quotes `value` if it contains characters which are special to CSV
+fn csv_field(value: &str) -> String {
+    match value.contains(&[',', '"', '\n', '\r'][..]) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) mod hex_hash {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(xxhash: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:016x}", xxhash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let xxhash = String::deserialize(deserializer)?;
+
+        u64::from_str_radix(&xxhash, 16).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::wad::{EntryDataFormat, Wad, WadBuilder, WadHashtable};
+
+    fn create_wad(major: u8, minor: u8) -> Wad<Cursor<Vec<u8>>> {
+        let mut builder = WadBuilder::with_version(major, minor).unwrap();
+        builder
+            .add_entry(1, b"first", EntryDataFormat::Raw)
+            .unwrap();
+        builder
+            .add_entry(u64::MAX, &b"second".repeat(16), EntryDataFormat::Zstd)
+            .unwrap();
+
+        builder.mount()
+    }
+
+    #[test]
+    fn test_manifest() {
+        let mut hashtable = WadHashtable::new();
+        hashtable.insert(1, "data/first, \"quoted\".bin".to_string());
+
+        let wad = create_wad(3, 1);
+        let manifest = wad.manifest(Some(&hashtable));
+        let entries = manifest.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].path().as_deref(),
+            Some("data/first, \"quoted\".bin")
+        );
+        assert_eq!(entries[0].data_offset(), 272 + 2 * 32);
+        assert_eq!(entries[1].xxhash(), u64::MAX);
+        assert_eq!(entries[1].data_format(), EntryDataFormat::Zstd);
+        assert_eq!(entries[1].uncompressed_size(), 96);
+        assert_eq!(entries[1].checksum().as_ref().map(String::len), Some(16));
+
+        let mut csv = Vec::new();
+        manifest.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            format!(
+                "0000000000000001,\"data/first, \"\"quoted\"\".bin\",Raw,5,5,{},336",
+                entries[0].checksum().as_ref().unwrap()
+            )
+        );
+        assert!(lines[2].starts_with("ffffffffffffffff,,Zstd,"));
+
+        // v1 archives don't store checksums
+        let manifest = create_wad(1, 1).manifest(None);
+        assert!(manifest
+            .entries()
+            .iter()
+            .all(|entry| entry.checksum().is_none() && entry.path().is_none()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let wad = create_wad(3, 1);
+        let manifest = wad.manifest(None);
+
+        let mut json = Vec::new();
+        manifest.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains(r#""xxhash": "ffffffffffffffff""#));
+        assert_eq!(
+            serde_json::from_str::<crate::wad::WadManifest>(&json).unwrap(),
+            manifest
+        );
+
+        let json = serde_json::to_value(&wad).unwrap();
+        assert_eq!(json["header"]["major"], 3);
+        assert_eq!(json["entries"][1]["xxhash"], "ffffffffffffffff");
+        assert_eq!(json["entries"][1]["data_format"], "Zstd");
+        assert!(json["entries"][0]["data_checksum"]["XxHash3"].is_array());
+        assert!(json["subchunk_toc"].is_null());
+    }
+}
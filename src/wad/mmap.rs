@@ -0,0 +1,48 @@
+use memmap2::Mmap;
+use std::{fs::File, io::Cursor, path::Path};
+
+use super::{Wad, WadError};
+
+impl Wad<Cursor<Mmap>> {
+    /// Mounts the WAD at `path` by memory mapping it
+    ///
+    /// Entry data can then be borrowed with [`Wad::entry_raw_data`] and decompressed with [`Wad::entry_data`]
+    /// without going through a buffered reader. The file must not be modified while it is mounted.
+    pub fn mount_mapped(path: &Path) -> Result<Self, WadError> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only and the caller guarantees the file isn't modified while it is mounted
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let mut wad = Self::mount_from_buffer(mmap)?;
+        wad.load_subchunk_toc_for_path(path)?;
+
+        Ok(wad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::wad::{EntryDataFormat, Wad, WadBuilder, WadError};
+
+    #[test]
+    fn test_mount_mapped() {
+        let data = b"memory mapped data ".repeat(64);
+        let mut builder = WadBuilder::new();
+        builder.add_entry(1, &data, EntryDataFormat::Zstd).unwrap();
+        builder.add_entry(2, &data, EntryDataFormat::Raw).unwrap();
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Test.wad.client");
+        fs::write(&path, builder.to_buffer()).unwrap();
+
+        let wad = Wad::mount_mapped(&path).unwrap();
+        assert_eq!(wad.entry_raw_data(2).unwrap(), data.as_slice());
+        assert_eq!(wad.entry_data(1).unwrap(), data);
+        assert!(matches!(
+            wad.entry_raw_data(3),
+            Err(WadError::EntryNotFound(3))
+        ));
+    }
+}
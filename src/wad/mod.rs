@@ -0,0 +1,1120 @@
+use flate2::read::GzDecoder;
+use getset::{CopyGetters, Getters};
+use num_enum::TryFromPrimitive;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{hash_map, HashMap},
+    convert::TryFrom,
+    fs::{self, File},
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+use thiserror::Error;
+use xxhash_rust::xxh3::xxh3_64;
+
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+use crate::streaming::{binary_reader::BinaryReader, binary_writer::BinaryWriter};
+
+pub use builder::{WadBuilder, WadWriteSummary};
+pub use compression::{CompressionPolicy, EntryCompression};
+pub use diff::{diff, EntryDiff, EntryDiffKind, EntrySizes, WadDiff};
+pub use editor::WadEditor;
+pub use entry_reader::EntryReader;
+pub use extract::ExtractionSummary;
+pub use game_index::{GameIndex, IndexedWad};
+pub use hash::{hash_path, normalize_path};
+pub use hashtable::WadHashtable;
+pub use header::WadHeader;
+pub use manifest::{ManifestEntry, WadManifest};
+#[cfg(feature = "rayon")]
+pub use parallel::{CancellationToken, ExtractionProgress};
+pub use redirection::{decode_redirection_target, load_redirected_entry_data};
+pub use subchunk::{subchunk_toc_path, WadSubchunk};
+pub use verify::{EntryVerification, WadVerificationReport};
+pub use vfs::{ShadowedEntry, WadVfs};
+
+mod builder;
+mod compression;
+mod diff;
+mod editor;
+mod entry_reader;
+mod extract;
+mod game_index;
+mod hash;
+mod hashtable;
+mod header;
+mod manifest;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod redirection;
+mod subchunk;
+mod verify;
+mod vfs;
+
+#[derive(Error, Debug)]
+pub enum WadError {
+    #[error("{0}")]
+    IoError(io::Error),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Unsupported version: {0}.{1}")]
+    UnsupportedVersion(u8, u8),
+    #[error("An entry with the same path hash already exists: {0}")]
+    DuplicateEntry(u64),
+    #[error("Unknown entry data format: {0}")]
+    UnknownEntryDataFormat(u8),
+    #[error("Entry not found: {0:016x}")]
+    EntryNotFound(u64),
+    #[error("Unsupported entry data format: {0:?}")]
+    UnsupportedEntryDataFormat(EntryDataFormat),
+    #[error("Decompressed size mismatch: expected {0} bytes, got {1}")]
+    DecompressedSizeMismatch(usize, usize),
+    #[error("Data offset out of range: {0}")]
+    DataOffsetOutOfRange(u64),
+    #[error("Entry is too large: {0:016x}")]
+    EntryTooLarge(u64),
+    #[error("Missing subchunk TOC for entry: {0:016x}")]
+    MissingSubchunkToc(u64),
+    #[error("Invalid subchunk data for entry: {0:016x}")]
+    InvalidSubchunkData(u64),
+    #[error("Invalid redirection for entry: {0:016x}")]
+    InvalidRedirection(u64),
+    #[error("Redirection cycle at entry: {0:016x}")]
+    RedirectionCycle(u64),
+    #[error("Invalid hashtable line: {0}")]
+    InvalidHashtableLine(usize),
+    #[error("Unsupported hashtable version: {0}")]
+    UnsupportedHashtableVersion(u8),
+    #[error("Unsupported game index cache version: {0}")]
+    UnsupportedGameIndexCacheVersion(u8),
+    #[error("Path is too long: {0}")]
+    PathTooLong(String),
+    #[error("The operation was cancelled")]
+    Cancelled,
+    #[error("Invalid TOC entry size: {0}")]
+    InvalidTocEntrySize(u16),
+    #[error(
+        "The TOC of {entry_count} entries doesn't fit in the {file_size} bytes of the archive"
+    )]
+    InvalidEntryCount { entry_count: u32, file_size: u64 },
+    #[error("Invalid TOC entry {index} at offset {offset}: {source}")]
+    InvalidTocEntry {
+        index: usize,
+        offset: u64,
+        source: Box<WadError>,
+    },
+    #[error("Invalid size of entry {index} ({xxhash:016x}): {size}")]
+    InvalidEntrySize {
+        index: usize,
+        xxhash: u64,
+        size: i32,
+    },
+    #[error("The data of entry {index} ({xxhash:016x}) at offset {offset} with size {size} extends past the end of the archive")]
+    EntryDataOutOfBounds {
+        index: usize,
+        xxhash: u64,
+        offset: u64,
+        size: u64,
+    },
+    #[error("The data of entry {index} ({xxhash:016x}) at offset {offset} overlaps the data of entry {other_xxhash:016x}")]
+    OverlappingEntryData {
+        index: usize,
+        xxhash: u64,
+        offset: u64,
+        other_xxhash: u64,
+    },
+}
+
+impl From<io::Error> for WadError {
+    fn from(error: io::Error) -> Self {
+        WadError::IoError(error)
+    }
+}
+
+/// The largest buffer allocated up front for the decompressed data of an entry
+const MAX_PREALLOCATION: usize = 64 * 1024 * 1024;
+
+#[derive(Getters)]
+pub struct Wad<R: Read + Seek = File> {
+    #[getset(get = "pub")]
+    header: WadHeader,
+
+    #[getset(get = "pub")]
+    entries: HashMap<u64, Entry>,
+    /// The path hashes of the entries in the order they are stored in the TOC
+    #[getset(get = "pub")]
+    toc_order: Vec<u64>,
+
+    #[getset(get = "pub")]
+    subchunk_toc: Option<Vec<WadSubchunk>>,
+
+    source: BinaryReader<R>,
+}
+
+#[derive(Debug, Clone, Getters, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Entry {
+    /// Serialized as 16 hex digits, see [`ManifestEntry`]
+    #[getset(get_copy = "pub")]
+    #[cfg_attr(feature = "serde", serde(with = "manifest::hex_hash"))]
+    xxhash: u64,
+
+    #[getset(get_copy = "pub")]
+    compressed_size: i32,
+    #[getset(get_copy = "pub")]
+    uncompressed_size: i32,
+
+    #[getset(get_copy = "pub")]
+    data_format: EntryDataFormat,
+    #[getset(get = "pub")]
+    data_checksum: EntryDataChecksum,
+    #[getset(get_copy = "pub(crate)")]
+    data_offset: u32,
+    #[getset(get_copy = "pub")]
+    is_duplicated: bool,
+
+    #[getset(get_copy = "pub")]
+    subchunk_count: u8,
+    #[getset(get_copy = "pub")]
+    first_subchunk_index: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(u8)]
+pub enum EntryDataFormat {
+    Raw,
+    GZip,
+    FileRedirection,
+    Zstd,
+    ZstdMulti,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EntryDataChecksum {
+    Sha256(Vec<u8>),
+    XxHash3(Vec<u8>),
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EntryDataChecksumKind {
+    Sha256,
+    XxHash3,
+    None,
+}
+
+/// Serializes the header, the entries in TOC order and the subchunk TOC, the data of the entries is left out
+#[cfg(feature = "serde")]
+impl<R: Read + Seek> Serialize for Wad<R> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries: Vec<&Entry> = self
+            .toc_order
+            .iter()
+            .map(|xxhash| &self.entries[xxhash])
+            .collect();
+
+        let mut wad = serializer.serialize_struct("Wad", 3)?;
+        wad.serialize_field("header", &self.header)?;
+        wad.serialize_field("entries", &entries)?;
+        wad.serialize_field("subchunk_toc", &self.subchunk_toc)?;
+        wad.end()
+    }
+}
+
+impl Wad<File> {
+    /// Mounts the WAD at `path`
+    ///
+    /// If `path` points into a game `DATA` directory, the subchunk TOC of the WAD is loaded as well.
+    pub fn mount_from_path(path: &Path) -> Result<Self, WadError> {
+        let mut wad = Self::read(BinaryReader::from_location(path)?)?;
+        wad.load_subchunk_toc_for_path(path)?;
+
+        Ok(wad)
+    }
+}
+
+impl<T: AsRef<[u8]>> Wad<Cursor<T>> {
+    /// Mounts a WAD which is held in memory
+    pub fn mount_from_buffer(buffer: T) -> Result<Self, WadError> {
+        Self::mount(Cursor::new(buffer))
+    }
+
+    /// Returns the data of the entry with the given path hash as it is stored in the archive, without copying it
+    pub fn entry_raw_data(&self, xxhash: u64) -> Result<&[u8], WadError> {
+        let entry = self
+            .entries
+            .get(&xxhash)
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+        let start = entry.data_offset as usize;
+        let end = start + entry.compressed_size as usize;
+
+        self.source
+            .get_ref()
+            .get_ref()
+            .as_ref()
+            .get(start..end)
+            .ok_or(WadError::DataOffsetOutOfRange(end as u64))
+    }
+
+    /// Decompresses the data of the entry with the given path hash straight from the buffer of this WAD
+    pub fn entry_data(&self, xxhash: u64) -> Result<Vec<u8>, WadError> {
+        let raw_data = self.entry_raw_data(xxhash)?;
+
+        self.decompress_entry_data(&self.entries[&xxhash], raw_data)
+    }
+}
+
+impl<R: Read + Seek> Wad<R> {
+    /// Mounts the WAD stored at the start of `reader`
+    ///
+    /// The reader is kept by the WAD so that entry data can be loaded later on.
+    pub fn mount(reader: R) -> Result<Self, WadError> {
+        Self::read(BinaryReader::new(reader))
+    }
+
+    fn read(mut br: BinaryReader<R>) -> Result<Self, WadError> {
+        let file_size = br.seek(SeekFrom::End(0))?;
+        br.seek(SeekFrom::Start(0))?;
+        let (header, entry_count) = WadHeader::read(&mut br)?;
+
+        let toc_offset = header.toc_offset() as u64;
+        let toc_entry_size = header.toc_entry_size() as u64;
+        if header.toc_entry_size() < header.required_toc_entry_size() {
+            return Err(WadError::InvalidTocEntrySize(header.toc_entry_size()));
+        }
+        // Checked before allocating anything so that a corrupted count can't exhaust memory
+        if toc_offset + entry_count as u64 * toc_entry_size > file_size {
+            return Err(WadError::InvalidEntryCount {
+                entry_count,
+                file_size,
+            });
+        }
+
+        let mut entries = HashMap::<u64, Entry>::with_capacity(entry_count as usize);
+        let mut toc_order = Vec::with_capacity(entry_count as usize);
+        for index in 0..entry_count as usize {
+            let offset = toc_offset + index as u64 * toc_entry_size;
+            br.seek(SeekFrom::Start(offset))?;
+            let entry = Entry::read(&mut br, header.checksum_kind()).map_err(|error| {
+                WadError::InvalidTocEntry {
+                    index,
+                    offset,
+                    source: Box::new(error),
+                }
+            })?;
+            entry.validate(index, file_size)?;
+            toc_order.push(entry.xxhash());
+
+            match entries.entry(entry.xxhash()) {
+                hash_map::Entry::Occupied(_) => Err(WadError::DuplicateEntry(entry.xxhash())),
+                hash_map::Entry::Vacant(hashmap_entry) => Ok(hashmap_entry.insert(entry)),
+            }?;
+        }
+        validate_data_ranges(&entries, &toc_order)?;
+
+        Ok(Wad {
+            header,
+            entries,
+            toc_order,
+            subchunk_toc: None,
+            source: br,
+        })
+    }
+
+    /// Loads the subchunk TOC if `path` points into a game `DATA` directory and the WAD contains one
+    fn load_subchunk_toc_for_path(&mut self, path: &Path) -> Result<(), WadError> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        if let Some(data_directory) = path.to_ascii_lowercase().rfind("data/") {
+            let subchunk_toc_hash = hash_path(&subchunk_toc_path(&path[data_directory..]));
+            if self.entries.contains_key(&subchunk_toc_hash) {
+                self.load_subchunk_toc_by_hash(subchunk_toc_hash)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads the subchunk TOC of this WAD, `wad_path` is the path of the WAD relative to the game directory
+    pub fn load_subchunk_toc(&mut self, wad_path: &str) -> Result<(), WadError> {
+        self.load_subchunk_toc_by_hash(hash_path(&subchunk_toc_path(wad_path)))
+    }
+
+    /// Loads the subchunk TOC of this WAD from the entry with the given path hash
+    pub fn load_subchunk_toc_by_hash(&mut self, xxhash: u64) -> Result<(), WadError> {
+        let subchunk_toc = WadSubchunk::read_toc(&self.load_entry_data(xxhash)?)?;
+        self.subchunk_toc = Some(subchunk_toc);
+
+        Ok(())
+    }
+
+    /// Returns the entry with the given path, see [`hash_path`]
+    pub fn entry_by_path(&self, path: &str) -> Option<&Entry> {
+        self.entries.get(&hash_path(path))
+    }
+
+    /// Reads the data of the entry with the given path hash and decompresses it
+    pub fn load_entry_data(&mut self, xxhash: u64) -> Result<Vec<u8>, WadError> {
+        let entry = self
+            .entries
+            .get(&xxhash)
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+        let raw_data = Self::read_raw_data(&mut self.source, entry)?;
+
+        self.decompress_entry_data(entry, &raw_data)
+    }
+
+    /// Decodes the stored `data` of `entry`, resolving subchunks through the subchunk TOC of this WAD
+    pub fn decompress_entry_data(&self, entry: &Entry, data: &[u8]) -> Result<Vec<u8>, WadError> {
+        subchunk::decompress_entry_data(entry, data, self.subchunk_toc.as_deref())
+    }
+
+    /// Reads the data of the entry with the given path hash as it is stored in the archive
+    pub fn load_entry_raw_data(&mut self, xxhash: u64) -> Result<Vec<u8>, WadError> {
+        let entry = self
+            .entries
+            .get(&xxhash)
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+
+        Self::read_raw_data(&mut self.source, entry)
+    }
+
+    fn read_raw_data(source: &mut BinaryReader<R>, entry: &Entry) -> Result<Vec<u8>, WadError> {
+        source.seek(SeekFrom::Start(entry.data_offset as u64))?;
+
+        Ok(source.read_bytes(entry.compressed_size as usize)?)
+    }
+
+    /// Same as [`Wad::write`], writing to the file at `path`
+    ///
+    /// The archive is written to a temporary file next to `path` which then replaces it, so `path` may be the file
+    /// this WAD was mounted from.
+    pub fn write_to_path(&mut self, path: &Path) -> Result<(), WadError> {
+        write_through_temporary_file(path, |bw| self.write(bw))
+    }
+
+    /// Writes this WAD to `bw` exactly as it was read
+    ///
+    /// The header and the TOC are written from what was read, in the original TOC order. Everything else,
+    /// including the entry data and any space between it, is copied from the source as-is.
+    pub fn write<W: Write + Seek>(&mut self, bw: &mut BinaryWriter<W>) -> Result<(), WadError> {
+        self.header.write(bw, self.toc_order.len() as u32)?;
+
+        let toc_offset = self.header.toc_offset() as u64;
+        let toc_entry_size = self.header.toc_entry_size() as u64;
+        self.copy_source(bw, self.header.size(), toc_offset)?;
+        for index in 0..self.toc_order.len() {
+            let start = bw.position()?;
+            self.entries[&self.toc_order[index]].write(bw, self.header.checksum_kind())?;
+
+            // TOC entries may be padded, the padding is copied like any other unused space
+            let written = bw.position()? - start;
+            let source_start = toc_offset + index as u64 * toc_entry_size;
+            self.copy_source(bw, source_start + written, source_start + toc_entry_size)?;
+        }
+
+        let toc_end = toc_offset + self.toc_order.len() as u64 * toc_entry_size;
+        let source_end = self.source.seek(SeekFrom::End(0))?;
+        self.copy_source(bw, toc_end, source_end)?;
+
+        Ok(bw.flush()?)
+    }
+
+    /// Copies the bytes between `start` and `end` of the source to `bw`
+    fn copy_source<W: Write + Seek>(
+        &mut self,
+        bw: &mut BinaryWriter<W>,
+        start: u64,
+        end: u64,
+    ) -> Result<(), WadError> {
+        const CHUNK_SIZE: u64 = 64 * 1024;
+
+        self.source.seek(SeekFrom::Start(start))?;
+        let mut remaining = end.saturating_sub(start);
+        while remaining > 0 {
+            let chunk = self.source.read_bytes(remaining.min(CHUNK_SIZE) as usize)?;
+            bw.write_slice(&chunk)?;
+            remaining -= chunk.len() as u64;
+        }
+
+        Ok(())
+    }
+}
+
+impl Entry {
+    /// Creates an entry for the stored `data` at `data_offset`, computing its checksum of `checksum_kind`
+    pub(crate) fn new(
+        xxhash: u64,
+        data_offset: u32,
+        data: &[u8],
+        uncompressed_size: usize,
+        data_format: EntryDataFormat,
+        checksum_kind: EntryDataChecksumKind,
+    ) -> Result<Self, WadError> {
+        Ok(Entry {
+            xxhash,
+            compressed_size: i32::try_from(data.len())
+                .map_err(|_| WadError::EntryTooLarge(xxhash))?,
+            uncompressed_size: i32::try_from(uncompressed_size)
+                .map_err(|_| WadError::EntryTooLarge(xxhash))?,
+            data_format,
+            data_checksum: EntryDataChecksum::compute(checksum_kind, data),
+            data_offset,
+            is_duplicated: false,
+            subchunk_count: 0,
+            first_subchunk_index: 0,
+        })
+    }
+
+    pub(crate) fn read<R: Read + Seek>(
+        br: &mut BinaryReader<R>,
+        checksum_kind: EntryDataChecksumKind,
+    ) -> Result<Self, WadError> {
+        let xxhash = br.read_u64()?;
+        let data_offset = br.read_u32()?;
+        let compressed_size = br.read_i32()?;
+        let uncompressed_size = br.read_i32()?;
+        // The upper 4 bits of the data format hold the subchunk count of the entry
+        let data_format = br.read_u8()?;
+        let subchunk_count = data_format >> 4;
+        let data_format = match EntryDataFormat::try_from(data_format & 0x0F) {
+            Ok(value) => Ok(value),
+            Err(error) => Err(WadError::UnknownEntryDataFormat(error.number)),
+        }?;
+        let is_duplicated = br.read_u8()? == 1;
+        let first_subchunk_index = br.read_u16()?;
+
+        let data_checksum = match checksum_kind {
+            EntryDataChecksumKind::Sha256 => EntryDataChecksum::Sha256(br.read_bytes(8)?),
+            EntryDataChecksumKind::XxHash3 => EntryDataChecksum::XxHash3(br.read_bytes(8)?),
+            EntryDataChecksumKind::None => EntryDataChecksum::None,
+        };
+
+        Ok(Entry {
+            xxhash,
+            data_offset,
+            compressed_size,
+            uncompressed_size,
+            data_format,
+            is_duplicated,
+            data_checksum,
+            subchunk_count,
+            first_subchunk_index,
+        })
+    }
+
+    /// Checks that the sizes of this entry, which is stored at `index` in the TOC, are valid and that its data
+    /// lies within an archive of `file_size` bytes
+    fn validate(&self, index: usize, file_size: u64) -> Result<(), WadError> {
+        for &size in &[self.compressed_size, self.uncompressed_size] {
+            if size < 0 {
+                return Err(WadError::InvalidEntrySize {
+                    index,
+                    xxhash: self.xxhash,
+                    size,
+                });
+            }
+        }
+
+        let offset = self.data_offset as u64;
+        let size = self.compressed_size as u64;
+        if offset + size > file_size {
+            return Err(WadError::EntryDataOutOfBounds {
+                index,
+                xxhash: self.xxhash,
+                offset,
+                size,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The offset and size of the stored data, which identify data shared by duplicated entries
+    pub(crate) fn data_range(&self) -> (u32, i32) {
+        (self.data_offset, self.compressed_size)
+    }
+
+    /// Writes this entry as a TOC entry storing checksums of `checksum_kind`
+    pub(crate) fn write<W: Write + Seek>(
+        &self,
+        bw: &mut BinaryWriter<W>,
+        checksum_kind: EntryDataChecksumKind,
+    ) -> Result<(), WadError> {
+        bw.write_u64(self.xxhash)?;
+        bw.write_u32(self.data_offset)?;
+        bw.write_i32(self.compressed_size)?;
+        bw.write_i32(self.uncompressed_size)?;
+        bw.write_u8(self.data_format as u8 | self.subchunk_count << 4)?;
+        bw.write_u8(self.is_duplicated as u8)?;
+        bw.write_u16(self.first_subchunk_index)?;
+        match (checksum_kind, &self.data_checksum) {
+            (EntryDataChecksumKind::None, _) => 0,
+            (_, EntryDataChecksum::Sha256(checksum) | EntryDataChecksum::XxHash3(checksum)) => {
+                bw.write_slice(checksum)?
+            }
+            (_, EntryDataChecksum::None) => bw.write_u64(0)?,
+        };
+
+        Ok(())
+    }
+
+    /// Decodes the stored `data` of this entry according to its [`EntryDataFormat`]
+    ///
+    /// [`EntryDataFormat::ZstdMulti`] entries need the subchunk TOC of their WAD, see [`Wad::decompress_entry_data`]
+    pub fn decompress_data(&self, data: &[u8]) -> Result<Vec<u8>, WadError> {
+        let uncompressed_size = self.uncompressed_size as usize;
+        let uncompressed_data = match self.data_format {
+            EntryDataFormat::Raw => data.to_vec(),
+            EntryDataFormat::GZip => decode_bounded(GzDecoder::new(data), uncompressed_size)?,
+            EntryDataFormat::Zstd => {
+                decode_bounded(zstd::stream::Decoder::new(data)?, uncompressed_size)?
+            }
+            format => return Err(WadError::UnsupportedEntryDataFormat(format)),
+        };
+
+        if uncompressed_data.len() != uncompressed_size {
+            return Err(WadError::DecompressedSizeMismatch(
+                uncompressed_size,
+                uncompressed_data.len(),
+            ));
+        }
+
+        Ok(uncompressed_data)
+    }
+}
+
+/// Reads the decompressed data from `decoder`, expecting `uncompressed_size` bytes
+///
+/// The size comes from the archive, so it isn't trusted for more than a bounded preallocation. Decoding stops
+/// one byte past the expected size, which is enough to detect oversized data without inflating all of it.
+pub(crate) fn decode_bounded<D: Read>(
+    decoder: D,
+    uncompressed_size: usize,
+) -> Result<Vec<u8>, WadError> {
+    let mut uncompressed_data = Vec::with_capacity(uncompressed_size.min(MAX_PREALLOCATION));
+    decoder
+        .take(uncompressed_size as u64 + 1)
+        .read_to_end(&mut uncompressed_data)?;
+
+    Ok(uncompressed_data)
+}
+
+/// Writes a file with `write` to a temporary file next to `path` which then replaces it
+///
+/// `path` is left untouched until the file was written completely, so it may be the file an archive is read from.
+pub(crate) fn write_through_temporary_file<T>(
+    path: &Path,
+    write: impl FnOnce(&mut BinaryWriter<File>) -> Result<T, WadError>,
+) -> Result<T, WadError> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    let temporary_path = Path::new(&temporary_path);
+
+    let result = BinaryWriter::from_location(temporary_path)
+        .map_err(WadError::from)
+        .and_then(|mut bw| write(&mut bw));
+    match result {
+        Ok(value) => {
+            fs::rename(temporary_path, path)?;
+            Ok(value)
+        }
+        Err(error) => {
+            let _ = fs::remove_file(temporary_path);
+            Err(error)
+        }
+    }
+}
+
+/// Checks that the data of no two entries partially overlaps
+///
+/// Entries whose data is duplicated share the exact same range, which is allowed.
+fn validate_data_ranges(entries: &HashMap<u64, Entry>, toc_order: &[u64]) -> Result<(), WadError> {
+    let mut ranges: Vec<(u64, u64, usize, u64)> = toc_order
+        .iter()
+        .enumerate()
+        .map(|(index, xxhash)| (&entries[xxhash], index))
+        .filter(|(entry, _)| entry.compressed_size > 0)
+        .map(|(entry, index)| {
+            let offset = entry.data_offset as u64;
+            (
+                offset,
+                offset + entry.compressed_size as u64,
+                index,
+                entry.xxhash,
+            )
+        })
+        .collect();
+    ranges.sort_unstable();
+
+    // Any overlap shows up between neighbours once the ranges are sorted by their start
+    for pair in ranges.windows(2) {
+        let (start, end, _, other_xxhash) = pair[0];
+        let (next_start, next_end, index, xxhash) = pair[1];
+        if next_start < end && (next_start, next_end) != (start, end) {
+            return Err(WadError::OverlappingEntryData {
+                index,
+                xxhash,
+                offset: next_start,
+                other_xxhash,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+impl EntryDataChecksum {
+    /// Computes the checksum of the stored `data` of an entry, SHA-256 checksums are truncated to 8 bytes
+    pub fn compute(kind: EntryDataChecksumKind, data: &[u8]) -> Self {
+        match kind {
+            EntryDataChecksumKind::Sha256 => {
+                EntryDataChecksum::Sha256(Sha256::digest(data)[..8].to_vec())
+            }
+            EntryDataChecksumKind::XxHash3 => {
+                EntryDataChecksum::XxHash3(xxh3_64(data).to_le_bytes().to_vec())
+            }
+            EntryDataChecksumKind::None => EntryDataChecksum::None,
+        }
+    }
+
+    pub fn kind(&self) -> EntryDataChecksumKind {
+        match self {
+            EntryDataChecksum::Sha256(_) => EntryDataChecksumKind::Sha256,
+            EntryDataChecksum::XxHash3(_) => EntryDataChecksumKind::XxHash3,
+            EntryDataChecksum::None => EntryDataChecksumKind::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+    use std::path::Path;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use crate::streaming::{binary_reader::BinaryReader, binary_writer::BinaryWriter};
+    use crate::wad::{
+        hash_path, subchunk_toc_path, Entry, EntryCompression, EntryDataChecksumKind,
+        EntryDataFormat, Wad, WadError,
+    };
+
+    /// (path hash, data format byte, first subchunk index, stored data, uncompressed size)
+    type StoredEntry = (u64, u8, u16, Vec<u8>, usize);
+
+    fn create_wad(entries: &[(u64, EntryDataFormat, &[u8])]) -> Vec<u8> {
+        let stored_entries: Vec<StoredEntry> = entries
+            .iter()
+            .map(|(xxhash, format, data)| {
+                let stored_data = match format {
+                    EntryDataFormat::GZip => {
+                        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                        encoder.write_all(data).unwrap();
+                        encoder.finish().unwrap()
+                    }
+                    EntryDataFormat::Zstd => zstd::stream::encode_all(*data, 0).unwrap(),
+                    _ => data.to_vec(),
+                };
+
+                (*xxhash, *format as u8, 0, stored_data, data.len())
+            })
+            .collect();
+
+        create_wad_from_stored_entries(&stored_entries)
+    }
+
+    fn create_wad_from_stored_entries(stored_entries: &[StoredEntry]) -> Vec<u8> {
+        let mut buffer = b"RW".to_vec();
+        buffer.extend_from_slice(&[3, 1]);
+        buffer.extend_from_slice(&[0; 256]);
+        buffer.extend_from_slice(&0u64.to_le_bytes());
+        buffer.extend_from_slice(&(stored_entries.len() as u32).to_le_bytes());
+
+        let mut data_offset = buffer.len() + stored_entries.len() * 32;
+        for (xxhash, format, first_subchunk_index, stored_data, uncompressed_size) in stored_entries
+        {
+            buffer.extend_from_slice(&xxhash.to_le_bytes());
+            buffer.extend_from_slice(&(data_offset as u32).to_le_bytes());
+            buffer.extend_from_slice(&(stored_data.len() as i32).to_le_bytes());
+            buffer.extend_from_slice(&(*uncompressed_size as i32).to_le_bytes());
+            buffer.push(*format);
+            buffer.push(0);
+            buffer.extend_from_slice(&first_subchunk_index.to_le_bytes());
+            buffer.extend_from_slice(&0u64.to_le_bytes());
+
+            data_offset += stored_data.len();
+        }
+        for (_, _, _, stored_data, _) in stored_entries {
+            buffer.extend_from_slice(stored_data);
+        }
+
+        buffer
+    }
+
+    /// Creates an archive with everything a writer could lose: an unsorted TOC, a signature, a TOC checksum,
+    /// duplicated entries, a non-zero subchunk index and unused bytes around the TOC and the data
+    fn create_versioned_wad(major: u8, minor: u8) -> Vec<u8> {
+        let (header_size, toc_entry_size) = match major {
+            1 => (12, 24),
+            2 => (104, 32),
+            _ => (272, 32),
+        };
+        let toc_offset = match major {
+            3 => header_size,
+            _ => header_size + 4,
+        };
+        let data_offset = toc_offset + 3 * toc_entry_size + 2;
+
+        let mut buffer = b"RW".to_vec();
+        buffer.extend_from_slice(&[major, minor]);
+        match major {
+            1 => {}
+            2 => {
+                buffer.push(64);
+                buffer.extend((0..83).map(|i| i as u8 + 1));
+                buffer.extend_from_slice(&0x1122334455667788u64.to_le_bytes());
+            }
+            _ => {
+                buffer.extend((0..256).map(|i| i as u8));
+                buffer.extend_from_slice(&0x1122334455667788u64.to_le_bytes());
+            }
+        }
+        if major != 3 {
+            buffer.extend_from_slice(&(toc_offset as u16).to_le_bytes());
+            buffer.extend_from_slice(&(toc_entry_size as u16).to_le_bytes());
+        }
+        buffer.extend_from_slice(&3u32.to_le_bytes());
+        buffer.resize(toc_offset, 0xAA);
+
+        // Entries 1 and 2 share their data
+        let toc = [
+            (3u64, data_offset, false),
+            (1, data_offset + 8, false),
+            (2, data_offset + 8, true),
+        ];
+        for (xxhash, offset, is_duplicated) in toc.iter() {
+            buffer.extend_from_slice(&xxhash.to_le_bytes());
+            buffer.extend_from_slice(&(*offset as u32).to_le_bytes());
+            buffer.extend_from_slice(&5i32.to_le_bytes());
+            buffer.extend_from_slice(&5i32.to_le_bytes());
+            buffer.push(EntryDataFormat::Raw as u8);
+            buffer.push(*is_duplicated as u8);
+            buffer.extend_from_slice(&0x1234u16.to_le_bytes());
+            if major > 1 {
+                buffer.extend_from_slice(&[*xxhash as u8; 8]);
+            }
+        }
+        buffer.resize(data_offset, 0xBB);
+        buffer.extend_from_slice(b"third");
+        buffer.extend_from_slice(&[0xCC; 3]);
+        buffer.extend_from_slice(b"first");
+        buffer.extend_from_slice(b"trailing data");
+
+        buffer
+    }
+
+    #[test]
+    #[ignore = "requires a local League of Legends installation"]
+    fn test_read() {
+        let wad = Wad::mount_from_path(Path::new(
+            "C:/Riot Games/League of Legends/Game/DATA/FINAL/Champions/Aatrox.wad.client",
+        ));
+
+        assert!(wad.is_ok())
+    }
+
+    #[test]
+    fn test_mount() {
+        let buffer = create_wad(&[(1, EntryDataFormat::Zstd, b"data")]);
+
+        let mut wad = Wad::mount_from_buffer(buffer.as_slice()).unwrap();
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"data");
+        assert_eq!(wad.entry_data(1).unwrap(), b"data");
+
+        assert!(matches!(
+            Wad::mount_from_buffer(&buffer[..buffer.len() - 1]),
+            Err(WadError::EntryDataOutOfBounds {
+                index: 0,
+                xxhash: 1,
+                ..
+            })
+        ));
+
+        // The WAD is read from the start even if the reader was already advanced
+        let mut cursor = Cursor::new(buffer);
+        cursor.set_position(16);
+        let mut wad = Wad::mount(cursor).unwrap();
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_mount_invalid_toc() {
+        let buffer = create_wad(&[
+            (1, EntryDataFormat::Raw, b"first"),
+            (2, EntryDataFormat::Raw, b"second"),
+        ]);
+        let mount = |patch: &dyn Fn(&mut Vec<u8>)| {
+            let mut buffer = buffer.clone();
+            patch(&mut buffer);
+            Wad::mount_from_buffer(buffer).err().unwrap()
+        };
+
+        let error = mount(&|buffer| buffer[268..272].copy_from_slice(&u32::MAX.to_le_bytes()));
+        assert!(matches!(
+            error,
+            WadError::InvalidEntryCount {
+                entry_count: u32::MAX,
+                ..
+            }
+        ));
+
+        // The data format of the second entry
+        let error = mount(&|buffer| buffer[272 + 32 + 20] = 0x0F);
+        assert!(matches!(
+            error,
+            WadError::InvalidTocEntry {
+                index: 1,
+                offset: 304,
+                source,
+            } if matches!(*source, WadError::UnknownEntryDataFormat(0x0F))
+        ));
+
+        // The compressed size of the first entry
+        let error =
+            mount(&|buffer| buffer[272 + 12..272 + 16].copy_from_slice(&(-1i32).to_le_bytes()));
+        assert!(matches!(
+            error,
+            WadError::InvalidEntrySize {
+                index: 0,
+                xxhash: 1,
+                size: -1,
+            }
+        ));
+
+        // The data offset of the second entry
+        let error = mount(&|buffer| {
+            buffer[272 + 32 + 8..272 + 32 + 12].copy_from_slice(&u32::MAX.to_le_bytes())
+        });
+        assert!(matches!(
+            error,
+            WadError::EntryDataOutOfBounds {
+                index: 1,
+                xxhash: 2,
+                offset,
+                size: 6,
+            } if offset == u32::MAX as u64
+        ));
+
+        // Moves the data of the second entry into the data of the first one
+        let error = mount(&|buffer| {
+            buffer[272 + 32 + 8..272 + 32 + 12].copy_from_slice(&(272 + 64 + 2u32).to_le_bytes())
+        });
+        assert!(matches!(
+            error,
+            WadError::OverlappingEntryData {
+                index: 1,
+                xxhash: 2,
+                offset: 338,
+                other_xxhash: 1,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_mount_from_path() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Test.wad.client");
+        std::fs::write(&path, create_wad(&[(1, EntryDataFormat::Raw, b"data")])).unwrap();
+
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"data");
+        assert!(matches!(
+            Wad::mount_from_path(&directory.path().join("Missing.wad.client")),
+            Err(WadError::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_entry_data() {
+        let data = b"league toolkit entry data ".repeat(64);
+        let buffer = create_wad(&[
+            (1, EntryDataFormat::Raw, &data),
+            (2, EntryDataFormat::GZip, &data),
+            (3, EntryDataFormat::Zstd, &data),
+        ]);
+        let mut wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer))).unwrap();
+
+        for xxhash in 1..=3 {
+            assert_eq!(wad.load_entry_data(xxhash).unwrap(), data);
+        }
+        assert!(matches!(
+            wad.load_entry_data(4),
+            Err(WadError::EntryNotFound(4))
+        ));
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let versions = [
+            (1, 0),
+            (1, 1),
+            (2, 0),
+            (2, 1),
+            (3, 0),
+            (3, 1),
+            (3, 2),
+            (3, 3),
+            (3, 4),
+        ];
+        for (major, minor) in versions.iter() {
+            let buffer = create_versioned_wad(*major, *minor);
+            let mut wad = Wad::mount_from_buffer(buffer.as_slice()).unwrap();
+            assert_eq!(wad.toc_order(), &[3, 1, 2]);
+            assert!(wad.entries()[&2].is_duplicated());
+            assert_eq!(wad.load_entry_data(2).unwrap(), b"first");
+
+            let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+            wad.write(&mut bw).unwrap();
+            assert_eq!(
+                bw.into_inner().unwrap().into_inner(),
+                buffer,
+                "v{}.{} wasn't written as it was read",
+                major,
+                minor
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_padded_toc_entries() {
+        let mut buffer = b"RW".to_vec();
+        buffer.extend_from_slice(&[1, 1]);
+        buffer.extend_from_slice(&12u16.to_le_bytes());
+        buffer.extend_from_slice(&28u16.to_le_bytes());
+        buffer.extend_from_slice(&1u32.to_le_bytes());
+        buffer.extend_from_slice(&1u64.to_le_bytes());
+        buffer.extend_from_slice(&40u32.to_le_bytes());
+        buffer.extend_from_slice(&4i32.to_le_bytes());
+        buffer.extend_from_slice(&4i32.to_le_bytes());
+        buffer.extend_from_slice(&[EntryDataFormat::Raw as u8, 0, 0, 0]);
+        buffer.extend_from_slice(&[0xEE; 4]);
+        buffer.extend_from_slice(b"data");
+
+        let mut wad = Wad::mount_from_buffer(buffer.as_slice()).unwrap();
+        assert_eq!(wad.load_entry_data(1).unwrap(), b"data");
+
+        let mut bw = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        wad.write(&mut bw).unwrap();
+        assert_eq!(bw.into_inner().unwrap().into_inner(), buffer);
+    }
+
+    #[test]
+    fn test_write_to_mounted_path() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Test.wad.client");
+        let buffer = create_versioned_wad(3, 1);
+        std::fs::write(&path, &buffer).unwrap();
+
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        wad.write_to_path(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), buffer);
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_decompress_oversized_data() {
+        // A corrupted TOC declaring a tiny size mustn't make the whole stream be inflated
+        let data = vec![0; 1024 * 1024];
+        for &compression in &[EntryCompression::GZip, EntryCompression::Zstd(0)] {
+            let compressed_data = compression.compress(&data).unwrap();
+            let entry = Entry::new(
+                1,
+                0,
+                &compressed_data,
+                4,
+                compression.data_format(),
+                EntryDataChecksumKind::None,
+            )
+            .unwrap();
+
+            assert!(matches!(
+                entry.decompress_data(&compressed_data),
+                Err(WadError::DecompressedSizeMismatch(4, 5))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_load_entry_data_size_mismatch() {
+        let mut buffer = create_wad(&[(1, EntryDataFormat::Raw, b"data")]);
+        // Patch the uncompressed size of the only entry
+        buffer[272 + 16..272 + 20].copy_from_slice(&8i32.to_le_bytes());
+        let mut wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer))).unwrap();
+
+        assert!(matches!(
+            wad.load_entry_data(1),
+            Err(WadError::DecompressedSizeMismatch(8, 4))
+        ));
+    }
+
+    #[test]
+    fn test_load_subchunked_entry_data() {
+        let first_chunk = b"compressible subchunk ".repeat(64);
+        let second_chunk = b"raw subchunk".to_vec();
+        let compressed_first_chunk = zstd::bulk::compress(&first_chunk, 0).unwrap();
+
+        let mut subchunk_toc = Vec::new();
+        for (compressed_size, uncompressed_size) in [
+            (compressed_first_chunk.len(), first_chunk.len()),
+            (second_chunk.len(), second_chunk.len()),
+        ]
+        .iter()
+        {
+            subchunk_toc.extend_from_slice(&(*compressed_size as u32).to_le_bytes());
+            subchunk_toc.extend_from_slice(&(*uncompressed_size as u32).to_le_bytes());
+            subchunk_toc.extend_from_slice(&0u64.to_le_bytes());
+        }
+
+        let wad_path = "DATA/FINAL/Champions/Test.wad.client";
+        let subchunk_toc_hash = hash_path(&subchunk_toc_path(wad_path));
+        let uncompressed_data = [first_chunk.as_slice(), second_chunk.as_slice()].concat();
+        let buffer = create_wad_from_stored_entries(&[
+            (
+                1,
+                (2 << 4) | EntryDataFormat::ZstdMulti as u8,
+                0,
+                [compressed_first_chunk, second_chunk].concat(),
+                uncompressed_data.len(),
+            ),
+            (
+                subchunk_toc_hash,
+                EntryDataFormat::Raw as u8,
+                0,
+                subchunk_toc.clone(),
+                subchunk_toc.len(),
+            ),
+        ]);
+        let mut wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer))).unwrap();
+
+        assert_eq!(wad.entries()[&1].subchunk_count(), 2);
+        assert!(matches!(
+            wad.load_entry_data(1),
+            Err(WadError::MissingSubchunkToc(1))
+        ));
+
+        wad.load_subchunk_toc(wad_path).unwrap();
+        assert_eq!(wad.load_entry_data(1).unwrap(), uncompressed_data);
+
+        let mut streamed_data = Vec::new();
+        let mut reader = wad.entry_reader(1).unwrap();
+        reader.read_to_end(&mut streamed_data).unwrap();
+        assert_eq!(streamed_data, uncompressed_data);
+    }
+}
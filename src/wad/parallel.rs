@@ -0,0 +1,229 @@
+use getset::CopyGetters;
+use rayon::prelude::*;
+use std::{
+    fs,
+    io::{Read, Seek},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use super::{
+    extract::{plan_extraction, write_entry},
+    subchunk, ExtractionSummary, Wad, WadError, WadHashtable,
+};
+
+/// Signals a running parallel operation to stop, cloned tokens share their state
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
+pub struct ExtractionProgress {
+    /// The number of entries which were extracted or failed so far
+    #[getset(get_copy = "pub")]
+    completed: usize,
+    #[getset(get_copy = "pub")]
+    total: usize,
+}
+
+impl<R: Read + Seek + Send> Wad<R> {
+    /// Same as [`Wad::extract_all`], but entries are decompressed and written on the rayon thread pool
+    ///
+    /// `on_progress` is called after every entry. If `cancellation` is cancelled, the remaining entries
+    /// are skipped and [`WadError::Cancelled`] is returned.
+    pub fn extract_all_parallel<F>(
+        &mut self,
+        destination: &Path,
+        hashtable: &WadHashtable,
+        on_progress: F,
+        cancellation: &CancellationToken,
+    ) -> Result<ExtractionSummary, WadError>
+    where
+        F: Fn(ExtractionProgress) + Sync,
+    {
+        fs::create_dir_all(destination)?;
+
+        let plan = plan_extraction(self.entries.values(), hashtable);
+        let total = plan.len();
+        let completed = AtomicUsize::new(0);
+
+        // Reading from the source is serialized, decompressing and writing is not
+        let entries = &self.entries;
+        let subchunk_toc = self.subchunk_toc.as_deref();
+        let source = Mutex::new(&mut self.source);
+        let results: Vec<Option<Result<_, WadError>>> = plan
+            .par_iter()
+            .map(|(xxhash, target)| {
+                if cancellation.is_cancelled() {
+                    return None;
+                }
+
+                let entry = &entries[xxhash];
+                let result = {
+                    let mut source = source.lock().unwrap_or_else(|error| error.into_inner());
+                    Self::read_raw_data(&mut source, entry)
+                }
+                .and_then(|raw_data| {
+                    subchunk::decompress_entry_data(entry, &raw_data, subchunk_toc)
+                })
+                .and_then(|data| write_entry(destination, *xxhash, target, &data));
+
+                on_progress(ExtractionProgress {
+                    completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                });
+
+                Some(result)
+            })
+            .collect();
+
+        if cancellation.is_cancelled() {
+            return Err(WadError::Cancelled);
+        }
+
+        let mut summary = ExtractionSummary::default();
+        for ((xxhash, _), result) in plan.into_iter().zip(results) {
+            match result {
+                Some(Ok(path)) => summary.extracted.push((xxhash, path)),
+                Some(Err(error)) => summary.failed.push((xxhash, error)),
+                None => return Err(WadError::Cancelled),
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Cursor,
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use crate::wad::{
+        hash_path, CancellationToken, EntryDataFormat, Wad, WadBuilder, WadError, WadHashtable,
+    };
+
+    fn create_wad() -> (Wad<Cursor<Vec<u8>>>, WadHashtable) {
+        let mut builder = WadBuilder::new();
+        let mut hashtable = WadHashtable::new();
+        for i in 0..64u32 {
+            let data = format!("entry {} ", i).repeat(i as usize + 1);
+            if i % 4 == 0 {
+                builder
+                    .add_entry(i as u64, data.as_bytes(), EntryDataFormat::GZip)
+                    .unwrap();
+            } else {
+                let path = format!("data/{}/{}.bin", i % 3, i);
+                builder
+                    .add_path_entry(&path, data.as_bytes(), EntryDataFormat::Zstd)
+                    .unwrap();
+                hashtable.insert(hash_path(&path), path);
+            }
+        }
+
+        (builder.mount(), hashtable)
+    }
+
+    fn read_tree(root: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut files = Vec::new();
+        let mut directories = vec![root.to_path_buf()];
+        while let Some(directory) = directories.pop() {
+            for entry in fs::read_dir(directory).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    directories.push(path);
+                } else {
+                    let relative_path = path.strip_prefix(root).unwrap().to_string_lossy();
+                    files.push((relative_path.into_owned(), fs::read(&path).unwrap()));
+                }
+            }
+        }
+        files.sort();
+
+        files
+    }
+
+    #[test]
+    fn test_extract_all_parallel() {
+        let (mut wad, hashtable) = create_wad();
+
+        let sequential_destination = tempfile::tempdir().unwrap();
+        let sequential_summary = wad
+            .extract_all(sequential_destination.path(), &hashtable)
+            .unwrap();
+
+        let parallel_destination = tempfile::tempdir().unwrap();
+        let progress_count = AtomicUsize::new(0);
+        let parallel_summary = wad
+            .extract_all_parallel(
+                parallel_destination.path(),
+                &hashtable,
+                |progress| {
+                    assert_eq!(progress.total(), 64);
+                    progress_count.fetch_add(1, Ordering::Relaxed);
+                },
+                &CancellationToken::new(),
+            )
+            .unwrap();
+
+        assert_eq!(progress_count.load(Ordering::Relaxed), 64);
+        assert_eq!(parallel_summary.extracted().len(), 64);
+        assert_eq!(
+            read_tree(sequential_destination.path()),
+            read_tree(parallel_destination.path())
+        );
+
+        let relative_paths = |summary: &crate::wad::ExtractionSummary, root: &Path| {
+            summary
+                .extracted()
+                .iter()
+                .map(|(xxhash, path)| (*xxhash, path.strip_prefix(root).unwrap().to_path_buf()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            relative_paths(&sequential_summary, sequential_destination.path()),
+            relative_paths(&parallel_summary, parallel_destination.path())
+        );
+    }
+
+    #[test]
+    fn test_extract_all_parallel_cancelled() {
+        let (mut wad, hashtable) = create_wad();
+        let cancellation = CancellationToken::new();
+        let destination = tempfile::tempdir().unwrap();
+
+        let result = wad.extract_all_parallel(
+            destination.path(),
+            &hashtable,
+            |progress| {
+                if progress.completed() == 8 {
+                    cancellation.cancel();
+                }
+            },
+            &cancellation,
+        );
+        assert!(matches!(result, Err(WadError::Cancelled)));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    collections::HashSet,
+    io::{Cursor, Read, Seek},
+};
+
+use crate::streaming::binary_reader::BinaryReader;
+
+use super::{hash_path, EntryDataFormat, Wad, WadError};
+
+impl<R: Read + Seek> Wad<R> {
+    /// Returns the target path of the [`EntryDataFormat::FileRedirection`] entry with the given path hash
+    pub fn load_redirection_target(&mut self, xxhash: u64) -> Result<String, WadError> {
+        let entry = self
+            .entries
+            .get(&xxhash)
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+        if entry.data_format() != EntryDataFormat::FileRedirection {
+            return Err(WadError::UnsupportedEntryDataFormat(entry.data_format()));
+        }
+
+        let raw_data = Self::read_raw_data(&mut self.source, entry)?;
+        decode_redirection_target(&raw_data).ok_or(WadError::InvalidRedirection(xxhash))
+    }
+}
+
+/// Decodes the length-prefixed target path stored in a redirection entry
+pub fn decode_redirection_target(data: &[u8]) -> Option<String> {
+    let mut br = BinaryReader::from_buffer(Cursor::new(data.to_vec()));
+    let length = br.read_u32().ok()? as usize;
+    if length > data.len() - 4 {
+        return None;
+    }
+
+    br.read_string(length).ok()
+}
+
+/// Loads the data of the entry with the given path hash from the first WAD in `wads` which contains it,
+/// following redirections until an entry with actual data is found
+pub fn load_redirected_entry_data<R: Read + Seek>(
+    wads: &mut [Wad<R>],
+    xxhash: u64,
+) -> Result<Vec<u8>, WadError> {
+    let mut visited = HashSet::new();
+    let mut xxhash = xxhash;
+    loop {
+        if !visited.insert(xxhash) {
+            return Err(WadError::RedirectionCycle(xxhash));
+        }
+
+        let wad = wads
+            .iter_mut()
+            .find(|wad| wad.entries.contains_key(&xxhash))
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+        match wad.entries[&xxhash].data_format() {
+            EntryDataFormat::FileRedirection => {
+                let target = wad.load_redirection_target(xxhash)?;
+                xxhash = hash_path(&target);
+            }
+            _ => return wad.load_entry_data(xxhash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xxhash_rust::xxh64::xxh64;
+
+    use crate::wad::{
+        decode_redirection_target, load_redirected_entry_data, EntryDataFormat, WadBuilder,
+        WadError,
+    };
+
+    fn redirection(target: &str) -> Vec<u8> {
+        let mut data = (target.len() as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(target.as_bytes());
+
+        data
+    }
+
+    #[test]
+    fn test_decode_redirection_target() {
+        assert_eq!(
+            decode_redirection_target(&redirection("DATA/Target.bin")).as_deref(),
+            Some("DATA/Target.bin")
+        );
+        assert_eq!(decode_redirection_target(&[8, 0, 0, 0, b'a']), None);
+        assert_eq!(decode_redirection_target(&[1]), None);
+    }
+
+    #[test]
+    fn test_load_redirected_entry_data() {
+        let mut mod_builder = WadBuilder::new();
+        mod_builder
+            .add_entry(
+                1,
+                &redirection("DATA/Redirect.bin"),
+                EntryDataFormat::FileRedirection,
+            )
+            .unwrap();
+        mod_builder
+            .add_path_entry(
+                "DATA/Redirect.bin",
+                &redirection("DATA/Target.bin"),
+                EntryDataFormat::FileRedirection,
+            )
+            .unwrap();
+        mod_builder
+            .add_entry(
+                2,
+                &redirection("DATA/Missing.bin"),
+                EntryDataFormat::FileRedirection,
+            )
+            .unwrap();
+
+        let mut base_builder = WadBuilder::new();
+        base_builder
+            .add_path_entry("data/target.bin", b"target", EntryDataFormat::Zstd)
+            .unwrap();
+
+        let mut wads = vec![mod_builder.mount(), base_builder.mount()];
+        assert_eq!(
+            wads[0].load_redirection_target(1).unwrap(),
+            "DATA/Redirect.bin"
+        );
+        assert_eq!(load_redirected_entry_data(&mut wads, 1).unwrap(), b"target");
+        assert!(matches!(
+            load_redirected_entry_data(&mut wads, 2),
+            Err(WadError::EntryNotFound(xxhash)) if xxhash == xxh64(b"data/missing.bin", 0)
+        ));
+    }
+
+    #[test]
+    fn test_redirection_cycle() {
+        let mut builder = WadBuilder::new();
+        builder
+            .add_path_entry("a", &redirection("b"), EntryDataFormat::FileRedirection)
+            .unwrap();
+        builder
+            .add_path_entry("b", &redirection("a"), EntryDataFormat::FileRedirection)
+            .unwrap();
+
+        let mut wads = vec![builder.mount()];
+        assert!(matches!(
+            load_redirected_entry_data(&mut wads, xxh64(b"a", 0)),
+            Err(WadError::RedirectionCycle(_))
+        ));
+    }
+}
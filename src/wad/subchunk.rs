@@ -0,0 +1,205 @@
+use getset::CopyGetters;
+use std::io::Cursor;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::streaming::binary_reader::BinaryReader;
+
+use super::{decode_bounded, Entry, EntryDataFormat, WadError, MAX_PREALLOCATION};
+
+const SUBCHUNK_TOC_ENTRY_SIZE: usize = 16;
+
+/// An entry of the `.subchunktoc` of a WAD which describes a single subchunk of a
+/// [`EntryDataFormat::ZstdMulti`](super::EntryDataFormat::ZstdMulti) entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WadSubchunk {
+    #[getset(get_copy = "pub")]
+    compressed_size: u32,
+    #[getset(get_copy = "pub")]
+    uncompressed_size: u32,
+    #[getset(get_copy = "pub")]
+    checksum: u64,
+}
+
+impl WadSubchunk {
+    pub(crate) fn read_toc(data: &[u8]) -> Result<Vec<Self>, WadError> {
+        let mut br = BinaryReader::from_buffer(Cursor::new(data.to_vec()));
+        let count = data.len() / SUBCHUNK_TOC_ENTRY_SIZE;
+
+        let mut subchunks = Vec::with_capacity(count);
+        for _ in 0..count {
+            subchunks.push(WadSubchunk {
+                compressed_size: br.read_u32()?,
+                uncompressed_size: br.read_u32()?,
+                checksum: br.read_u64()?,
+            });
+        }
+
+        Ok(subchunks)
+    }
+
+    /// Decodes the stored `data` of this subchunk
+    pub(crate) fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, WadError> {
+        let uncompressed_size = self.uncompressed_size as usize;
+        // Subchunks which don't benefit from compression are stored as-is
+        let uncompressed_data = if self.compressed_size == self.uncompressed_size {
+            data.to_vec()
+        } else {
+            decode_bounded(zstd::stream::Decoder::new(data)?, uncompressed_size)?
+        };
+
+        if uncompressed_data.len() != uncompressed_size {
+            return Err(WadError::DecompressedSizeMismatch(
+                uncompressed_size,
+                uncompressed_data.len(),
+            ));
+        }
+
+        Ok(uncompressed_data)
+    }
+}
+
+/// Returns the path of the `.subchunktoc` entry belonging to the WAD at `wad_path`
+///
+/// `wad_path` is expected to be relative to the game directory, eg. `DATA/FINAL/Champions/Aatrox.wad.client`
+pub fn subchunk_toc_path(wad_path: &str) -> String {
+    let wad_path = wad_path.replace('\\', "/").to_lowercase();
+    let wad_path = wad_path
+        .strip_suffix(".client")
+        .or_else(|| wad_path.strip_suffix(".mobile"))
+        .unwrap_or(&wad_path);
+
+    format!("{}.subchunktoc", wad_path)
+}
+
+/// Decodes the stored `data` of `entry`, resolving subchunks through `subchunk_toc`
+pub(crate) fn decompress_entry_data(
+    entry: &Entry,
+    data: &[u8],
+    subchunk_toc: Option<&[WadSubchunk]>,
+) -> Result<Vec<u8>, WadError> {
+    match (entry.data_format(), subchunk_toc) {
+        (EntryDataFormat::ZstdMulti, Some(subchunk_toc)) => {
+            decompress_subchunked_data(entry, data, subchunk_toc)
+        }
+        (EntryDataFormat::ZstdMulti, None) => Err(WadError::MissingSubchunkToc(entry.xxhash())),
+        _ => entry.decompress_data(data),
+    }
+}
+
+/// Returns the subchunks of `entry`
+pub(crate) fn entry_subchunks<'a>(
+    entry: &Entry,
+    subchunk_toc: &'a [WadSubchunk],
+) -> Result<&'a [WadSubchunk], WadError> {
+    let first_subchunk = entry.first_subchunk_index() as usize;
+
+    subchunk_toc
+        .get(first_subchunk..first_subchunk + entry.subchunk_count() as usize)
+        .ok_or_else(|| WadError::InvalidSubchunkData(entry.xxhash()))
+}
+
+fn decompress_subchunked_data(
+    entry: &Entry,
+    data: &[u8],
+    subchunk_toc: &[WadSubchunk],
+) -> Result<Vec<u8>, WadError> {
+    let subchunks = entry_subchunks(entry, subchunk_toc)?;
+
+    let uncompressed_size = entry.uncompressed_size() as usize;
+    let mut uncompressed_data = Vec::with_capacity(uncompressed_size.min(MAX_PREALLOCATION));
+    let mut offset = 0;
+    for subchunk in subchunks {
+        let compressed_size = subchunk.compressed_size as usize;
+        let subchunk_data = data
+            .get(offset..offset + compressed_size)
+            .ok_or_else(|| WadError::InvalidSubchunkData(entry.xxhash()))?;
+
+        uncompressed_data.extend(subchunk.decompress(subchunk_data)?);
+        offset += compressed_size;
+    }
+
+    if offset != data.len() {
+        return Err(WadError::InvalidSubchunkData(entry.xxhash()));
+    }
+
+    if uncompressed_data.len() != uncompressed_size {
+        return Err(WadError::DecompressedSizeMismatch(
+            uncompressed_size,
+            uncompressed_data.len(),
+        ));
+    }
+
+    Ok(uncompressed_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress_subchunked_data, subchunk_toc_path, WadSubchunk};
+    use crate::wad::{Entry, EntryDataChecksumKind, EntryDataFormat, WadError};
+
+    fn subchunk(data: &[u8], uncompressed_size: u32) -> WadSubchunk {
+        WadSubchunk {
+            compressed_size: data.len() as u32,
+            uncompressed_size,
+            checksum: 0,
+        }
+    }
+
+    #[test]
+    fn test_decompress_subchunked_data() {
+        let first = zstd::bulk::compress(&[1; 64], 0).unwrap();
+        let second = b"raw";
+        let data = [&first[..], &second[..]].concat();
+        let subchunk_toc = [subchunk(&first, 64), subchunk(second, 3)];
+
+        let mut entry = Entry::new(
+            1,
+            0,
+            &data,
+            67,
+            EntryDataFormat::ZstdMulti,
+            EntryDataChecksumKind::None,
+        )
+        .unwrap();
+        entry.subchunk_count = 2;
+        let uncompressed_data = decompress_subchunked_data(&entry, &data, &subchunk_toc).unwrap();
+        assert_eq!(uncompressed_data, [&[1; 64][..], &second[..]].concat());
+
+        // Data left over after the last subchunk
+        let padded_data = [&data[..], &[0][..]].concat();
+        assert!(matches!(
+            decompress_subchunked_data(&entry, &padded_data, &subchunk_toc),
+            Err(WadError::InvalidSubchunkData(1))
+        ));
+    }
+
+    #[test]
+    fn test_decompress_size_mismatch() {
+        let data = zstd::bulk::compress(&[1; 64], 0).unwrap();
+
+        // A huge size from a corrupted subchunk TOC must not be allocated up front
+        assert!(matches!(
+            subchunk(&data, u32::MAX).decompress(&data),
+            Err(WadError::DecompressedSizeMismatch(_, 64))
+        ));
+        assert!(matches!(
+            subchunk(&data, 32).decompress(&data),
+            Err(WadError::DecompressedSizeMismatch(32, 33))
+        ));
+    }
+
+    #[test]
+    fn test_subchunk_toc_path() {
+        assert_eq!(
+            subchunk_toc_path("DATA/FINAL/Champions/Aatrox.wad.client"),
+            "data/final/champions/aatrox.wad.subchunktoc"
+        );
+        assert_eq!(
+            subchunk_toc_path("DATA\\FINAL\\Maps\\Shipping\\Map11.wad.mobile"),
+            "data/final/maps/shipping/map11.wad.subchunktoc"
+        );
+    }
+}
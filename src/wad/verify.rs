@@ -0,0 +1,173 @@
+use getset::Getters;
+use std::{
+    collections::BTreeMap,
+    io::{ErrorKind, Read, Seek},
+};
+
+use super::{EntryDataChecksum, Wad, WadError};
+
+/// The result of verifying the stored data of an entry against its checksum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryVerification {
+    Valid,
+    Mismatch {
+        expected: EntryDataChecksum,
+        actual: EntryDataChecksum,
+    },
+    /// The data of the entry extends past the end of the archive
+    Truncated,
+    /// The archive version doesn't store entry checksums
+    NoChecksum,
+}
+
+#[derive(Debug, Default, Getters)]
+pub struct WadVerificationReport {
+    #[getset(get = "pub")]
+    entries: BTreeMap<u64, EntryVerification>,
+}
+
+impl WadVerificationReport {
+    /// Returns `true` if none of the entries are mismatched or truncated
+    pub fn is_valid(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Returns the entries that are mismatched or truncated
+    pub fn failures(&self) -> impl Iterator<Item = (u64, &EntryVerification)> {
+        self.entries
+            .iter()
+            .filter(|(_, verification)| {
+                !matches!(
+                    verification,
+                    EntryVerification::Valid | EntryVerification::NoChecksum
+                )
+            })
+            .map(|(&xxhash, verification)| (xxhash, verification))
+    }
+}
+
+impl<R: Read + Seek> Wad<R> {
+    /// Verifies the stored data of the entry with the given path hash against its checksum
+    pub fn verify_entry(&mut self, xxhash: u64) -> Result<EntryVerification, WadError> {
+        let entry = self
+            .entries
+            .get(&xxhash)
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+        let expected = entry.data_checksum();
+        if let EntryDataChecksum::None = expected {
+            return Ok(EntryVerification::NoChecksum);
+        }
+
+        let raw_data = match Self::read_raw_data(&mut self.source, entry) {
+            Ok(raw_data) => raw_data,
+            Err(WadError::IoError(error)) if error.kind() == ErrorKind::UnexpectedEof => {
+                return Ok(EntryVerification::Truncated)
+            }
+            Err(error) => return Err(error),
+        };
+
+        let actual = EntryDataChecksum::compute(expected.kind(), &raw_data);
+        if &actual == expected {
+            Ok(EntryVerification::Valid)
+        } else {
+            Ok(EntryVerification::Mismatch {
+                expected: expected.clone(),
+                actual,
+            })
+        }
+    }
+
+    /// Verifies the stored data of every entry against its checksum
+    pub fn verify(&mut self) -> Result<WadVerificationReport, WadError> {
+        let mut xxhashes: Vec<u64> = self.entries.keys().copied().collect();
+        xxhashes.sort_unstable();
+
+        let mut report = WadVerificationReport::default();
+        for xxhash in xxhashes {
+            report.entries.insert(xxhash, self.verify_entry(xxhash)?);
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::streaming::binary_reader::BinaryReader;
+    use crate::wad::{
+        EntryDataChecksum, EntryDataChecksumKind, EntryDataFormat, EntryVerification, Wad,
+        WadBuilder,
+    };
+
+    fn build() -> Vec<u8> {
+        let mut builder = WadBuilder::new();
+        builder
+            .add_entry(1, &b"first".repeat(32), EntryDataFormat::Zstd)
+            .unwrap();
+        builder
+            .add_entry(2, b"second", EntryDataFormat::Raw)
+            .unwrap();
+
+        builder.to_buffer()
+    }
+
+    #[test]
+    fn test_compute_sha256() {
+        assert_eq!(
+            EntryDataChecksum::compute(EntryDataChecksumKind::Sha256, b"abc"),
+            EntryDataChecksum::Sha256(vec![0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea])
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let buffer = build();
+        let mut wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer))).unwrap();
+
+        let report = wad.verify().unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.entries().len(), 2);
+    }
+
+    #[test]
+    fn test_verify_corrupted() {
+        let mut buffer = build();
+        // The data of the second entry is stored last
+        let last = buffer.len() - 1;
+        buffer[last] ^= 0xFF;
+        let mut wad = Wad::read(BinaryReader::from_buffer(Cursor::new(buffer))).unwrap();
+
+        let report = wad.verify().unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(report.entries()[&1], EntryVerification::Valid);
+        assert!(matches!(
+            report.entries()[&2],
+            EntryVerification::Mismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn test_verify_truncated() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Test.wad.client");
+        let buffer = build();
+        std::fs::write(&path, &buffer).unwrap();
+
+        // Truncated archives are rejected when mounting, so the file is truncated afterwards
+        let mut wad = Wad::mount_from_path(&path).unwrap();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(buffer.len() as u64 - 2)
+            .unwrap();
+
+        let report = wad.verify().unwrap();
+        assert_eq!(
+            report.failures().collect::<Vec<_>>(),
+            vec![(2, &EntryVerification::Truncated)]
+        );
+    }
+}
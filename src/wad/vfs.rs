@@ -0,0 +1,159 @@
+use getset::{CopyGetters, Getters};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{Read, Seek},
+};
+
+use super::{Entry, Wad, WadError};
+
+/// A stack of WADs in which entries of WADs mounted later override entries of WADs mounted earlier
+#[derive(Getters)]
+pub struct WadVfs<R: Read + Seek = File> {
+    /// The mounted WADs, from the lowest to the highest priority
+    #[getset(get = "pub")]
+    layers: Vec<Wad<R>>,
+
+    /// The index of the layer that provides each entry
+    lookup: HashMap<u64, usize>,
+}
+
+/// An entry which exists in more than one layer of a [`WadVfs`]
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+pub struct ShadowedEntry {
+    /// The index of the layer the entry is resolved from
+    #[getset(get_copy = "pub")]
+    winner: usize,
+    /// The indices of the layers whose entry is overridden, from the highest to the lowest priority
+    #[getset(get = "pub")]
+    shadowed: Vec<usize>,
+}
+
+impl<R: Read + Seek> Default for WadVfs<R> {
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            lookup: HashMap::new(),
+        }
+    }
+}
+
+impl<R: Read + Seek> WadVfs<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mounts `wad` on top of the stack, so that its entries override the ones of every WAD mounted before
+    ///
+    /// Returns the index of the new layer.
+    pub fn mount(&mut self, wad: Wad<R>) -> usize {
+        let layer = self.layers.len();
+        self.lookup
+            .extend(wad.entries.keys().map(|&xxhash| (xxhash, layer)));
+        self.layers.push(wad);
+
+        layer
+    }
+
+    /// Returns the index of the layer the entry with the given path hash is resolved from, together with the entry
+    pub fn resolve(&self, xxhash: u64) -> Option<(usize, &Entry)> {
+        let layer = *self.lookup.get(&xxhash)?;
+
+        Some((layer, &self.layers[layer].entries[&xxhash]))
+    }
+
+    /// Loads the decompressed data of the highest priority entry with the given path hash
+    pub fn load_entry_data(&mut self, xxhash: u64) -> Result<Vec<u8>, WadError> {
+        let layer = *self
+            .lookup
+            .get(&xxhash)
+            .ok_or(WadError::EntryNotFound(xxhash))?;
+
+        self.layers[layer].load_entry_data(xxhash)
+    }
+
+    /// Iterates the path hashes of every entry in the stack together with the layer they are resolved from
+    pub fn entries(&self) -> impl Iterator<Item = (u64, usize)> + '_ {
+        self.lookup.iter().map(|(&xxhash, &layer)| (xxhash, layer))
+    }
+
+    /// Returns the entries which are overridden by a higher priority layer, keyed by path hash
+    pub fn shadowed_entries(&self) -> BTreeMap<u64, ShadowedEntry> {
+        let mut shadowed_entries = BTreeMap::new();
+        for (layer, wad) in self.layers.iter().enumerate().rev() {
+            for &xxhash in wad.entries.keys() {
+                let winner = self.lookup[&xxhash];
+                if winner != layer {
+                    shadowed_entries
+                        .entry(xxhash)
+                        .or_insert_with(|| ShadowedEntry {
+                            winner,
+                            shadowed: Vec::new(),
+                        })
+                        .shadowed
+                        .push(layer);
+                }
+            }
+        }
+
+        shadowed_entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.lookup.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lookup.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::wad::{EntryDataFormat, Wad, WadBuilder, WadError, WadVfs};
+
+    fn create_wad(entries: &[(u64, &[u8])]) -> Wad<Cursor<Vec<u8>>> {
+        let mut builder = WadBuilder::new();
+        for (xxhash, data) in entries {
+            builder
+                .add_entry(*xxhash, data, EntryDataFormat::Zstd)
+                .unwrap();
+        }
+
+        builder.mount()
+    }
+
+    #[test]
+    fn test_vfs() {
+        let mut vfs = WadVfs::new();
+        assert!(vfs.is_empty());
+        assert_eq!(
+            vfs.mount(create_wad(&[(1, b"base"), (2, b"base"), (3, b"base")])),
+            0
+        );
+        assert_eq!(vfs.mount(create_wad(&[(1, b"mod"), (2, b"mod")])), 1);
+        assert_eq!(vfs.mount(create_wad(&[(1, b"patch"), (4, b"patch")])), 2);
+
+        assert_eq!(vfs.len(), 4);
+        assert_eq!(vfs.resolve(1).map(|(layer, _)| layer), Some(2));
+        assert_eq!(vfs.resolve(3).map(|(layer, _)| layer), Some(0));
+        assert!(vfs.resolve(5).is_none());
+
+        assert_eq!(vfs.load_entry_data(1).unwrap(), b"patch");
+        assert_eq!(vfs.load_entry_data(2).unwrap(), b"mod");
+        assert_eq!(vfs.load_entry_data(3).unwrap(), b"base");
+        assert!(matches!(
+            vfs.load_entry_data(5),
+            Err(WadError::EntryNotFound(5))
+        ));
+
+        let shadowed_entries = vfs.shadowed_entries();
+        assert_eq!(shadowed_entries.len(), 2);
+        assert_eq!(shadowed_entries[&1].winner(), 2);
+        assert_eq!(shadowed_entries[&1].shadowed(), &[1, 0]);
+        assert_eq!(shadowed_entries[&2].winner(), 1);
+        assert_eq!(shadowed_entries[&2].shadowed(), &[0]);
+    }
+}